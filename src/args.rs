@@ -0,0 +1,209 @@
+use std::path::Path;
+use std::error::Error;
+use std::fmt;
+use std::env;
+
+use crate::value::Value;
+use crate::env::{ coerce, Guessed };
+
+/// Overrides of config values from command line arguments like `--set database.port=5433`,
+/// along with the config file picked with `--config path` and the profile picked with `--profile name`.
+///
+/// Unlike environment variables, every key has to exist in the config and values have to convert to the type of the value they override
+#[derive(Clone, Debug, Default)]
+pub struct ArgOverrides {
+    sets: Vec<(String, String)>,
+    config: Option<Box<Path>>,
+    profile: Option<String>
+}
+
+impl ArgOverrides {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Picks `--set path=value`, `--config path` and `--profile name`, or the same with `=` after the flag, out of `args`,
+    /// ignoring every other argument
+    pub fn parse<I, S>(args: I) -> Result<Self, ArgError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>
+    {
+        let mut overrides: Self = Self::new();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let arg: &str = arg.as_ref();
+            let ( name, inline ): (&str, Option<&str>) = match arg.split_once('=') {
+                Some(( name, value )) if name.starts_with("--") => ( name, Some(value) ),
+                _ => ( arg, None )
+            };
+
+            if name != "--set" && name != "--config" && name != "--profile" {
+                continue;
+            }
+
+            let value: String = match inline {
+                Some(value) => String::from(value),
+                None => match args.next() {
+                    Some(value) => String::from(value.as_ref()),
+                    None => return Err(ArgError::MissingValue(String::from(name)))
+                }
+            };
+
+            if name == "--config" {
+                overrides.config = Some(Path::new(&value).into());
+            } else if name == "--profile" {
+                overrides.profile = Some(value);
+            } else {
+                match value.split_once('=') {
+                    Some(( path, __value )) if !path.is_empty() => overrides.sets.push(( String::from(path), String::from(__value) )),
+                    _ => return Err(ArgError::InvalidSet(value))
+                }
+            }
+        }
+
+        Ok(overrides)
+    }
+
+    /// Same as `parse`, with the arguments of the process
+    pub fn from_env() -> Result<Self, ArgError> {
+        Self::parse(env::args().skip(1))
+    }
+
+    /// Overrides the value at `path`, like `--set path=value`
+    pub fn set(mut self, path: &str, value: &str) -> Self {
+        self.sets.push(( String::from(path), String::from(value) ));
+        self
+    }
+
+    /// Path given with `--config`, to read instead of the default config file
+    pub fn config(&self) -> Option<&Path> {
+        self.config.as_deref()
+    }
+
+    /// Profile given with `--profile`, see `Profiles`
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// Overrides values of `value` in the order they were given,
+    /// returning the paths of the values overridden
+    pub fn apply(&self, value: &mut Value) -> Result<Vec<String>, ArgError> {
+        self.apply_guessing(value, &mut Guessed::default())
+    }
+
+    /// Same as `apply`, recording the values whose type was guessed in `guessed`
+    pub(crate) fn apply_guessing(&self, value: &mut Value, guessed: &mut Guessed) -> Result<Vec<String>, ArgError> {
+        let mut overridden: Vec<String> = Vec::new();
+
+        for ( path, set_value ) in &self.sets {
+            let existing: &Value = value.get(path).ok_or_else(|| ArgError::UnknownKey(path.clone()))?;
+            let __set_value: Value = coerce(Some(existing), set_value).map_err(|expected| ArgError::TypeMismatch {
+                path: path.clone(),
+                value: set_value.clone(),
+                expected
+            })?;
+
+            guessed.record(path, set_value, Some(existing));
+            value.set(path, __set_value);
+            overridden.push(path.clone());
+        }
+
+        Ok(overridden)
+    }
+
+}
+
+/// Error returned when command line overrides can't be parsed or applied
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgError {
+    /// `--set`, `--config` or `--profile` was the last argument
+    MissingValue(String),
+    /// `--set` wasn't followed by `path=value`
+    InvalidSet(String),
+    /// `--set` a path the config doesn't have
+    UnknownKey(String),
+    /// `--set` a value that can't be converted to the type of the value it overrides
+    TypeMismatch {
+        path: String,
+        value: String,
+        expected: &'static str
+    }
+}
+
+impl fmt::Display for ArgError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArgError::MissingValue(arg) => write!(f, "{} is missing a value", arg),
+            ArgError::InvalidSet(set) => write!(f, "--set {} is not of the form path=value", set),
+            ArgError::UnknownKey(path) => write!(f, "unknown key {}", path),
+            ArgError::TypeMismatch { path, value, expected } => write!(f, "{} is set to {:?}, expected {}", path, value, expected)
+        }
+    }
+
+}
+
+impl Error for ArgError {}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn value() -> Value {
+        let mut v: Value = Value::default();
+
+        v.set("database.host", Value::from("localhost"));
+        v.set("database.port", Value::from(5432));
+
+        v
+    }
+
+    #[test]
+    fn parse_args() {
+        let a: ArgOverrides = ArgOverrides::parse(vec![
+            "-v", "--set", "database.port=5433", "--config=app.toml", "--set=database.host=db=1", "input.txt", "--profile", "prod"
+        ]).unwrap();
+
+        assert_eq!(a.config(), Some(Path::new("app.toml")));
+        assert_eq!(a.profile(), Some("prod"));
+        assert_eq!(a.sets, vec![
+            ( String::from("database.port"), String::from("5433") ),
+            ( String::from("database.host"), String::from("db=1") )
+        ]);
+        assert_eq!(ArgOverrides::parse(vec![ "--config" ]).err(), Some(ArgError::MissingValue(String::from("--config"))));
+        assert_eq!(ArgOverrides::parse(vec![ "--set", "port" ]).err(), Some(ArgError::InvalidSet(String::from("port"))));
+    }
+
+    #[test]
+    fn apply() {
+        let mut v: Value = value();
+        let overridden: Vec<String> = ArgOverrides::new()
+            .set("database.port", "5433")
+            .apply(&mut v)
+            .unwrap();
+
+        assert_eq!(v.get("database.port"), Some(&Value::from(5433)));
+        assert_eq!(overridden, vec![ "database.port" ]);
+    }
+
+    #[test]
+    fn apply_errors() {
+        assert_eq!(
+            ArgOverrides::new().set("database.user", "admin").apply(&mut value()).err(),
+            Some(ArgError::UnknownKey(String::from("database.user")))
+        );
+        assert_eq!(
+            ArgOverrides::new().set("database.port", "default").apply(&mut value()).err(),
+            Some(ArgError::TypeMismatch {
+                path: String::from("database.port"),
+                value: String::from("default"),
+                expected: "integer"
+            })
+        );
+    }
+
+}
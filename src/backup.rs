@@ -0,0 +1,262 @@
+use std::path::{ Path, PathBuf };
+use std::time::{ SystemTime, UNIX_EPOCH };
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+
+/// How to back up a file before it's overwritten
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Backups {
+    /// Don't keep backups
+    #[default]
+    None,
+    /// Keep up to that many numbered copies next to the file,
+    /// `config.toml.1.bak` being the newest
+    Numbered(usize),
+    /// Keep up to `keep` copies named after the time they were made in `dir`, like `config.toml.1700000000000.bak`,
+    /// or every copy if `keep` is 0.
+    ///
+    /// A relative `dir` is relative to the directory of the file
+    Timestamped {
+        dir: Box<Path>,
+        keep: usize
+    }
+}
+
+impl Backups {
+
+    /// Whether an entry of a directory of configs is a backup made with this policy, or the directory holding them
+    pub(crate) fn is_backup(&self, path: &Path) -> bool {
+        let file_name: &str = &path.file_name().unwrap_or_default().to_string_lossy();
+
+        match self {
+            Backups::None => false,
+            Backups::Numbered(_) => file_name.ends_with(".bak"),
+            Backups::Timestamped { dir, .. } => {
+                let backups: PathBuf = backup_dir(path, dir);
+
+                backups == path || (Some(&*backups) == path.parent() && is_timestamped(file_name))
+            }
+        }
+    }
+
+}
+
+fn file_name(path: &Path) -> OsString {
+    path.file_name().unwrap_or_default().to_os_string()
+}
+
+fn numbered_path(path: &Path, number: usize) -> PathBuf {
+    let mut name: OsString = file_name(path);
+
+    name.push(format!(".{}.bak", number));
+
+    path.with_file_name(name)
+}
+
+fn backup_dir(path: &Path, dir: &Path) -> PathBuf {
+    match path.parent() {
+        Some(parent) => parent.join(dir),
+        None => dir.to_path_buf()
+    }
+}
+
+/// Timestamp of a timestamped backup of the file named `name`, if `backup_name` is one
+fn timestamp(name: &str, backup_name: &str) -> Option<u128> {
+    backup_name.strip_prefix(name)?
+        .strip_prefix('.')?
+        .strip_suffix(".bak")?
+        .parse()
+        .ok()
+}
+
+/// Whether `backup_name` looks like the name of a timestamped backup of any file
+fn is_timestamped(backup_name: &str) -> bool {
+    backup_name.strip_suffix(".bak")
+        .and_then(|name| name.rsplit_once('.'))
+        .is_some_and(|( _, millis )| millis.parse::<u128>().is_ok())
+}
+
+/// Copies the file at `path` to a new backup, dropping backups past what the policy keeps
+pub(crate) fn backup(path: &Path, backups: &Backups) -> io::Result<()> {
+    match backups {
+        Backups::None | Backups::Numbered(0) => Ok(()),
+        Backups::Numbered(count) => {
+            let _ = fs::remove_file(numbered_path(path, *count));
+
+            for number in (1..*count).rev() {
+                let from: PathBuf = numbered_path(path, number);
+
+                if from.is_file() {
+                    fs::rename(&from, numbered_path(path, number + 1))?;
+                }
+            }
+
+            fs::copy(path, numbered_path(path, 1))?;
+            Ok(())
+        },
+        Backups::Timestamped { dir, keep } => {
+            let dir: PathBuf = backup_dir(path, dir);
+            let mut millis: u128 = SystemTime::now().duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_millis())
+                .unwrap_or(0);
+            let mut backup_path: PathBuf;
+
+            fs::create_dir_all(&dir)?;
+
+            // Two backups within the same millisecond still get a name of their own
+            loop {
+                let mut name: OsString = file_name(path);
+
+                name.push(format!(".{}.bak", millis));
+                backup_path = dir.join(name);
+
+                if !backup_path.exists() {
+                    break;
+                }
+
+                millis += 1;
+            }
+
+            fs::copy(path, backup_path)?;
+
+            if *keep > 0 {
+                for old in list(path, backups)?.into_iter().skip(*keep) {
+                    fs::remove_file(old)?;
+                }
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// Backups of the file at `path` made with the policy, newest first
+pub(crate) fn list(path: &Path, backups: &Backups) -> io::Result<Vec<Box<Path>>> {
+    match backups {
+        Backups::None => Ok(Vec::new()),
+        Backups::Numbered(count) => Ok((1..=*count)
+            .map(|number| numbered_path(path, number))
+            .filter(|backup_path| backup_path.is_file())
+            .map(PathBuf::into_boxed_path)
+            .collect()),
+        Backups::Timestamped { dir, .. } => {
+            let dir: PathBuf = backup_dir(path, dir);
+            let name: String = file_name(path).to_string_lossy().into_owned();
+            let mut found: Vec<(u128, Box<Path>)> = Vec::new();
+
+            if !dir.is_dir() {
+                return Ok(Vec::new());
+            }
+
+            for entry in fs::read_dir(&dir)? {
+                let entry: fs::DirEntry = entry?;
+
+                if let Some(millis) = timestamp(&name, &entry.file_name().to_string_lossy()) {
+                    found.push(( millis, entry.path().into_boxed_path() ));
+                }
+            }
+
+            found.sort_by(|( a, _ ), ( b, _ )| b.cmp(a));
+
+            Ok(found.into_iter().map(|( _, backup_path )| backup_path).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test::test_path::TestPath;
+    use crate::test::child_path::ChildPath;
+
+    fn backup_contents(path: &Path, backups: &Backups) -> Vec<String> {
+        list(path, backups).unwrap()
+            .iter()
+            .map(|backup_path| fs::read_to_string(backup_path).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn numbered_rotates() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.child_path("test.txt");
+        let b: Backups = Backups::Numbered(2);
+
+        fs::create_dir_all(&tp.path).unwrap();
+
+        for s in [ "1", "2", "3" ] {
+            fs::write(p, s).unwrap();
+            backup(p, &b).unwrap();
+        }
+
+        assert_eq!(backup_contents(p, &b), vec![ "3", "2" ]);
+        assert!(!numbered_path(p, 3).exists());
+    }
+
+    #[test]
+    fn timestamped_keeps_newest() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.child_path("test.txt");
+        let b: Backups = Backups::Timestamped {
+            dir: Path::new("backups").into(),
+            keep: 2
+        };
+
+        fs::create_dir_all(&tp.path).unwrap();
+
+        for s in [ "1", "2", "3" ] {
+            fs::write(p, s).unwrap();
+            backup(p, &b).unwrap();
+        }
+
+        assert_eq!(backup_contents(p, &b), vec![ "3", "2" ]);
+        assert_eq!(fs::read_dir(tp.child_path("backups")).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn timestamped_keeps_all() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.child_path("test.txt");
+        let b: Backups = Backups::Timestamped {
+            dir: Path::new("backups").into(),
+            keep: 0
+        };
+
+        fs::create_dir_all(&tp.path).unwrap();
+
+        for s in [ "1", "2", "3" ] {
+            fs::write(p, s).unwrap();
+            backup(p, &b).unwrap();
+        }
+
+        assert_eq!(backup_contents(p, &b), vec![ "3", "2", "1" ]);
+    }
+
+    #[test]
+    fn is_backup() {
+        let b: Backups = Backups::Timestamped {
+            dir: Path::new("backups").into(),
+            keep: 2
+        };
+        let here: Backups = Backups::Timestamped {
+            dir: Path::new(".").into(),
+            keep: 2
+        };
+
+        assert!(b.is_backup(Path::new("configs/backups")));
+        assert!(!b.is_backup(Path::new("configs/test.txt.1700000000000.bak")));
+        assert!(here.is_backup(Path::new("configs/test.txt.1700000000000.bak")));
+        assert!(!here.is_backup(Path::new("configs/test.txt")));
+        assert!(Backups::Numbered(2).is_backup(Path::new("configs/test.txt.1.bak")));
+    }
+
+    #[test]
+    fn timestamped_ignores_other_files() {
+        assert_eq!(timestamp("test.txt", "test.txt.1700000000000.bak"), Some(1700000000000));
+        assert_eq!(timestamp("test.txt", "test.txt.old.bak"), None);
+        assert_eq!(timestamp("test", "test.txt.1700000000000.bak"), None);
+    }
+
+}
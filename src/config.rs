@@ -1,8 +1,14 @@
-use std::path::Path;
+use std::path::{ Path, PathBuf };
+use std::collections::BTreeMap;
+use std::io::{ self, Write };
 use std::fs;
-use std::io::Error;
+#[cfg(feature = "tokio")]
+use std::future::Future;
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
 
-use crate::format;
+use crate::format::{ self, Defaulted };
+use crate::error::{ ConfigError, Operation };
 use crate::config_file::ConfigFile;
 use crate::config_directory::ConfigDirectory;
 
@@ -11,15 +17,217 @@ pub enum Config<Format: format::Format + Sized + Clone> {
     Directory(ConfigDirectory<Format>)
 }
 
+impl<Format: format::Format + Sized + Clone> Clone for Config<Format>
+where
+    Format::Content: Clone
+{
+
+    fn clone(&self) -> Self {
+        match self {
+            Config::File(config_file) => Config::File(config_file.clone()),
+            Config::Directory(config_dir) => Config::Directory(config_dir.clone())
+        }
+    }
+
+}
+
+impl<Format: format::Format + Sized + Clone> Config<Format> {
+
+    pub fn defaulted(&self) -> bool {
+        match self {
+            Config::File(config_file) => config_file.defaulted.is_defaulted(),
+            Config::Directory(config_dir) => config_dir.defaulted.is_defaulted()
+        }
+    }
+
+}
+
+#[cfg(feature = "tokio")]
+impl<Format> Config<Format>
+where
+    Format: format::Format + Sized + Clone + Send + 'static,
+    Format::Content: Send,
+    Format::Defaults: Send
+{
+
+    // Boxed, since directories read their children through here and async functions can't recurse otherwise
+    pub(crate) fn read_async(self) -> Pin<Box<dyn Future<Output = Result<Self, ConfigError>> + Send>> {
+        Box::pin(async move {
+            match self {
+                Config::File(config_file) => Ok(Config::File(config_file.read_async().await?)),
+                Config::Directory(config_dir) => Ok(Config::Directory(config_dir.read_async().await?))
+            }
+        })
+    }
+
+    pub(crate) fn write_async(self) -> Pin<Box<dyn Future<Output = Result<Self, ConfigError>> + Send>> {
+        Box::pin(async move {
+            match self {
+                Config::File(config_file) => Ok(Config::File(config_file.write_async().await?)),
+                Config::Directory(config_dir) => Ok(Config::Directory(config_dir.write_async().await?))
+            }
+        })
+    }
+
+}
+
+/// Files of a directory tree that were filled in from defaults,
+/// by their path relative to the directory like `server/database.toml`, along with what of them was defaulted
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DefaultedReport {
+    configs: BTreeMap<String, Defaulted>
+}
+
+impl DefaultedReport {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds what was defaulted of the config named `name`, including the files of directories
+    pub(crate) fn add<Format: format::Format + Sized + Clone>(&mut self, name: &str, config: &Config<Format>) {
+        match config {
+            Config::File(config_file) => if config_file.defaulted.is_defaulted() {
+                self.configs.insert(String::from(name), config_file.defaulted.clone());
+            },
+            Config::Directory(config_dir) => for ( path, defaulted ) in config_dir.defaulted.iter() {
+                self.configs.insert(format!("{}/{}", name, path), defaulted.clone());
+            }
+        }
+    }
+
+    pub fn is_defaulted(&self) -> bool {
+        !self.configs.is_empty()
+    }
+
+    /// What was defaulted of the file at `path`, if anything
+    pub fn get(&self, path: &str) -> Option<&Defaulted> {
+        self.configs.get(path)
+    }
+
+    /// Defaulted files, sorted by path
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Defaulted)> {
+        self.configs.iter().map(|( path, defaulted )| ( path.as_str(), defaulted ))
+    }
+
+    /// Paths of every defaulted key, like `server/database.toml:database.port`,
+    /// or just the path of the file when all of it was defaulted
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = Vec::new();
+
+        for ( path, defaulted ) in self.iter() {
+            match defaulted {
+                Defaulted::Keys(__keys) => keys.extend(__keys.iter().map(|key| format!("{}:{}", path, key))),
+                _ => keys.push(String::from(path))
+            }
+        }
+
+        keys
+    }
+
+    pub fn len(&self) -> usize {
+        self.configs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.configs.is_empty()
+    }
+
+}
+
+/// Runs blocking file system work on tokio's blocking thread pool, passing on any panic
+#[cfg(feature = "tokio")]
+pub(crate) async fn blocking<T, F>(f: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static
+{
+    config_task(tokio::task::spawn_blocking(f).await)
+}
+
+/// Output of a finished task of a `JoinSet`, passing on any panic
+#[cfg(feature = "tokio")]
+pub(crate) fn joined<T>(joined: Option<Result<T, tokio::task::JoinError>>) -> Option<T> {
+    joined.map(config_task)
+}
+
+#[cfg(feature = "tokio")]
+fn config_task<T>(joined: Result<T, tokio::task::JoinError>) -> T {
+    match joined {
+        Ok(output) => output,
+        Err(err) => match err.try_into_panic() {
+            Ok(panic) => std::panic::resume_unwind(panic),
+            Err(err) => panic!("config task failed: {}", err)
+        }
+    }
+}
+
 /// Ensures that directory and it's ancestors exists
-pub(crate) fn ensure(path: &Path) -> Result<(), Error> {
+pub(crate) fn ensure(path: &Path) -> Result<(), ConfigError> {
     if !path.is_dir() {
-        fs::create_dir_all(&path)?;
+        fs::create_dir_all(path).map_err(|err| ConfigError::io(path, Operation::CreateDir, err))?;
     }
 
     Ok(())
 }
 
+/// Writes `contents` to a temporary file next to `path`, syncs it to disk and renames it over `path`,
+/// so `path` either keeps it's old contents or holds all of the new ones, even if we crash mid-write
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temp_path: PathBuf = temp_path(path);
+    let written: io::Result<()> = (|| {
+        let mut file: fs::File = fs::File::create(&temp_path)?;
+
+        // Keep the permissions of the file we're replacing
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    })();
+
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    written?;
+    sync_parent(path)
+}
+
+/// Whether a file is a temporary file of an atomic write or a lock file,
+/// which are kept next to configs but aren't configs themselves
+pub(crate) fn is_temporary(file_name: &str) -> bool {
+    file_name.starts_with('.') && (file_name.ends_with(".tmp") || file_name.ends_with(".lock"))
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut file_name: std::ffi::OsString = std::ffi::OsString::from(".");
+
+    file_name.push(path.file_name().unwrap_or_default());
+    file_name.push(format!(".{}.tmp", std::process::id()));
+
+    path.with_file_name(file_name)
+}
+
+/// Syncs the directory holding `path`, so the rename itself survives a crash
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    let parent: &Path = match path.parent() {
+        Some(parent) if parent != Path::new("") => parent,
+        _ => Path::new(".")
+    };
+
+    fs::File::open(parent)?.sync_all()
+}
+
+/// Directories can't be opened for syncing outside of unix, renames are as durable as they get
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -31,7 +239,7 @@ mod tests {
     fn ensure_self() {
         let p: &Path = &TestPath::new().path;
         
-        ensure(&p).unwrap();
+        ensure(p).unwrap();
         assert!(p.is_dir());
     }
 
@@ -40,8 +248,32 @@ mod tests {
         let tp: TestPath = TestPath::new();
         let p: &Path = &tp.child_path("test");
 
-        ensure(&p).unwrap();
+        ensure(p).unwrap();
         assert!(p.is_dir());
     }
 
+    #[test]
+    fn write_atomic_replaces() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.child_path("test.txt");
+
+        ensure(&tp.path).unwrap();
+        fs::write(p, "Goodbye, world!").unwrap();
+        write_atomic(p, b"Hello, world!").unwrap();
+
+        assert_eq!(fs::read(p).unwrap(), b"Hello, world!".to_vec());
+        assert_eq!(fs::read_dir(&tp.path).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn write_atomic_error_removes_temp() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.child_path("test");
+
+        ensure(p).unwrap();
+
+        assert!(write_atomic(p, b"Hello, world!").is_err());
+        assert_eq!(fs::read_dir(&tp.path).unwrap().count(), 1);
+    }
+
 }
\ No newline at end of file
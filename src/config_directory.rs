@@ -1,53 +1,108 @@
 use std::path::{ Path, PathBuf };
 use std::collections::HashMap;
-use std::io::Error;
 use std::fs;
 
+#[cfg(feature = "tokio")]
+use tokio::task::JoinSet;
+
 use crate::format;
-use crate::config_file::ConfigFile;
-use crate::config;
+use crate::config_file::{ ConfigFile, ConfigFileOpts };
+use crate::config::{ self, DefaultedReport };
+use crate::error::{ ConfigError, Operation };
+use crate::xdg::BaseDir;
 use config::Config;
 
+#[derive(Clone, Default)]
 pub struct ConfigDirOpts {
     pub write_if_defaulted: bool,
     pub read_new: bool,
-    pub recursive: bool
-}
-
-impl Default for ConfigDirOpts {
-
-    fn default() -> Self {
-        Self {
-            write_if_defaulted: false,
-            read_new: false,
-            recursive: false
-        }
-    }
-
+    pub recursive: bool,
+    /// Options of the files found by `read_new`. Backups made with their policy aren't read as configs themselves
+    pub files: ConfigFileOpts
 }
 
 pub struct ConfigDirectory<Format: format::Format + Sized + Clone> {
     pub path: Box<Path>,
     pub configs: HashMap<String, Config<Format>>,
-    pub defaulted: bool,
+    /// Files of the directory, and of nested directories, that were filled in from defaults by the last read
+    pub defaulted: DefaultedReport,
+    /// Directories read from, in order, for children that don't exist in `path`. Writes always go to `path`
+    pub fallbacks: Vec<Box<Path>>,
 
     format: Format,
     options: ConfigDirOpts
 }
 
+impl<Format: format::Format + Sized + Clone> Clone for ConfigDirectory<Format>
+where
+    Format::Content: Clone
+{
+
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            configs: self.configs.clone(),
+            defaulted: self.defaulted.clone(),
+            fallbacks: self.fallbacks.clone(),
+
+            format: self.format.clone(),
+            options: self.options.clone()
+        }
+    }
+
+}
+
 impl<Format: format::Format + Sized + Clone> ConfigDirectory<Format> {
 
     pub fn new(path: &Path, format: Format) -> Self {
         Self {
             path: path.to_path_buf().into_boxed_path(),
             configs: HashMap::new(),
-            defaulted: false,
+            defaulted: DefaultedReport::new(),
+            fallbacks: Vec::new(),
 
             format,
             options: ConfigDirOpts::default()
         }
     }
 
+    /// Directory of `app_name` in the XDG config directories,
+    /// each child read from the first of `$XDG_CONFIG_HOME` and `$XDG_CONFIG_DIRS` that has it and written to `$XDG_CONFIG_HOME`.
+    /// `None` if the user's home can't be found
+    pub fn xdg(app_name: &str, format: Format) -> Option<Self> {
+        Self::xdg_in(BaseDir::Config, app_name, format)
+    }
+
+    /// Same as `xdg`, in the XDG data directories
+    pub fn xdg_data(app_name: &str, format: Format) -> Option<Self> {
+        Self::xdg_in(BaseDir::Data, app_name, format)
+    }
+
+    /// Same as `xdg`, in the XDG state directory
+    pub fn xdg_state(app_name: &str, format: Format) -> Option<Self> {
+        Self::xdg_in(BaseDir::State, app_name, format)
+    }
+
+    fn xdg_in(base_dir: BaseDir, app_name: &str, format: Format) -> Option<Self> {
+        let ( path, fallbacks ) = base_dir.paths(Path::new(app_name))?;
+        let mut config_dir: Self = Self::new(&path, format);
+
+        config_dir.fallbacks = fallbacks.into_iter().map(PathBuf::into_boxed_path).collect();
+
+        Some(config_dir)
+    }
+
+    /// Adds a directory to read children from when they don't exist in `path`, after the fallbacks added before it
+    pub fn fallback(mut self, path: &Path) -> Self {
+        self.fallbacks.push(path.into());
+        self
+    }
+
+    pub fn opt(mut self, options: ConfigDirOpts) -> Self {
+        self.options = options;
+        self
+    }
+
     fn child_path(&self, path: &Path) -> Box<Path> {
         let mut path_buf: PathBuf = PathBuf::new();
 
@@ -76,18 +131,12 @@ impl<Format: format::Format + Sized + Clone> ConfigDirectory<Format> {
     #[allow(clippy::ptr_arg)]
     fn has_config(&self, path: &Path) -> bool {
         for config in self.configs.values() {
-            let config_path: &Path;
-
-            match config {
-                Config::File(config_file) => {
-                    config_path = &config_file.path;
-                },
-                Config::Directory(config_dir) => {
-                    config_path = &config_dir.path;
-                }
-            }
+            let config_path: &Path = match config {
+                Config::File(config_file) => &config_file.path,
+                Config::Directory(config_dir) => &config_dir.path
+            };
 
-            if config_path == &*path {
+            if config_path == path {
                 return true;
             }
         }
@@ -99,16 +148,10 @@ impl<Format: format::Format + Sized + Clone> ConfigDirectory<Format> {
         let mut children: Vec<String> = vec![];
 
         for ( key, config ) in self.configs.iter() {
-            let config_path: &Path;
-
-            match config {
-                Config::File(config_file) => {
-                    config_path = &config_file.path;
-                },
-                Config::Directory(config_dir) => {
-                    config_path = &config_dir.path;
-                }
-            }
+            let config_path: &Path = match config {
+                Config::File(config_file) => &config_file.path,
+                Config::Directory(config_dir) => &config_dir.path
+            };
 
             if config_path.parent().unwrap() == &*self.path {
                 children.push(key.clone());
@@ -118,111 +161,141 @@ impl<Format: format::Format + Sized + Clone> ConfigDirectory<Format> {
         children
     }
 
-    pub fn read(mut self) -> Result<Self, Error> {
-        // We should only read new configs if read_new is enabled.
-        // If we're supposed to read new configs, we just insert any new configs found in our directory
-        // to be read in the next step bellow
-        if self.options.read_new && self.path.is_dir() {
-            for entry in fs::read_dir(&self.path)? {
-                let entry: fs::DirEntry = entry?;
-                let config_name: String = entry.file_name().into_string().unwrap();
-                let config_path: Box<Path> = self.child_path(Path::new(&config_name));
-
-                if !self.has_config(&config_path) {
-                    let file_type: fs::FileType = entry.file_type()?;
-
-                    if file_type.is_file() {
-                        self.configs.insert(config_name, Config::File(ConfigFile::new(&config_path, self.format.clone())));
-                    } else if file_type.is_dir() {
-                        self.configs.insert(config_name, Config::Directory(ConfigDirectory::new(&config_path, self.format.clone())));
-                    }
+    /// Gives a child the fallbacks of the directory, so it's read from them when it doesn't exist in `path`
+    fn inherit_fallbacks(&self, key: &str, config: &mut Config<Format>) {
+        let fallbacks: Vec<Box<Path>> = self.fallbacks.iter().map(|fallback| fallback.join(key).into_boxed_path()).collect();
+
+        if fallbacks.is_empty() {
+            return;
+        }
+
+        match config {
+            Config::File(config_file) => config_file.fallbacks = fallbacks,
+            Config::Directory(config_dir) => config_dir.fallbacks = fallbacks
+        }
+    }
+
+    /// Inserts any configs found in our directory, or its fallbacks, that we don't know of yet, to be read along with the rest
+    fn read_new(&mut self) -> Result<(), ConfigError> {
+        let dirs: Vec<Box<Path>> = std::iter::once(&self.path)
+            .chain(&self.fallbacks)
+            .filter(|dir| dir.is_dir())
+            .cloned()
+            .collect();
+
+        for dir in dirs {
+            self.read_new_in(&dir)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_new_in(&mut self, dir: &Path) -> Result<(), ConfigError> {
+        for entry in fs::read_dir(dir).map_err(|err| ConfigError::io(dir, Operation::ReadDir, err))? {
+            let entry: fs::DirEntry = entry.map_err(|err| ConfigError::io(dir, Operation::ReadDir, err))?;
+            let config_name: String = entry.file_name().into_string().unwrap();
+            let config_path: Box<Path> = self.child_path(Path::new(&config_name));
+
+            if !self.has_config(&config_path) && !self.options.files.backups.is_backup(&entry.path()) && !config::is_temporary(&config_name) {
+                let file_type: fs::FileType = entry.file_type().map_err(|err| ConfigError::io(&entry.path(), Operation::ReadDir, err))?;
+
+                if file_type.is_file() {
+                    let config_file: ConfigFile<Format> = ConfigFile::new(&config_path, self.format.clone())
+                        .opt(self.options.files.clone());
+
+                    self.configs.insert(config_name, Config::File(config_file));
+                } else if file_type.is_dir() {
+                    let config_dir: ConfigDirectory<Format> = ConfigDirectory::new(&config_path, self.format.clone())
+                        .opt(self.options.clone());
+
+                    self.configs.insert(config_name, Config::Directory(config_dir));
                 }
             }
         }
 
-        for key in self.children() {
-            // We should only read directory contents if recursive is enabled.
-            // Since the read functions require that we retrieve ownership,
-            // we want to be sure beforehand if we're supposed to read the config,
-            // which we figure out here
-            let config: &Config<Format> = self.configs.get(&key).unwrap();
-            let should_read: bool = if let Config::Directory(_) = config {
-                self.options.recursive
-            } else {
-                true
-            };
+        Ok(())
+    }
 
-            if should_read {
-                let config: Config<Format> = self.configs.remove(&key).unwrap();
-            //                               ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
-            //                               Here we retrieve ownership for the read functions
-                let reinsert_config: Config<Format>;
-                let defaulted: bool;
+    // We should only read directory contents if recursive is enabled.
+    // Since the read functions require that we retrieve ownership,
+    // we want to be sure beforehand if we're supposed to read the config,
+    // which we figure out here
+    fn should_read(&self, key: &str) -> bool {
+        match self.configs.get(key).unwrap() {
+            Config::Directory(_) => self.options.recursive,
+            Config::File(_) => true
+        }
+    }
 
-                match config {
-                    Config::File(mut config_file) => {
-                        config_file = config_file.read()?;
-                        defaulted = config_file.defaulted;
-                        reinsert_config = Config::File(config_file);
-                    },
-                    Config::Directory(mut config_dir) => {
-                        config_dir = config_dir.read()?;
-                        defaulted = config_dir.defaulted;
-                        reinsert_config = Config::Directory(config_dir);
-                    }
-                }
+    // Same goes for writing
+    fn should_write(&self, key: &str) -> Result<bool, ConfigError> {
+        match self.configs.get(key).unwrap() {
+            Config::Directory(config_dir) => if self.options.recursive {
+                Ok(true)
+            } else {
+                config::ensure(&config_dir.path)?;
+            //  ^^^^^^^^^^^^^^^^^^^^^ If we're not going to write directory contents,
+            //                        we still want the directory to be made
+                Ok(false)
+            },
+            Config::File(_) => Ok(true)
+        }
+    }
 
-                if defaulted {
-                    self.defaulted = true;
-                }
+    pub fn read(mut self) -> Result<Self, ConfigError> {
+        // We should only read new configs if read_new is enabled.
+        // If we're supposed to read new configs, we just insert any new configs found in our directory
+        // to be read in the next step bellow
+        if self.options.read_new {
+            self.read_new()?;
+        }
 
+        let mut defaulted: DefaultedReport = DefaultedReport::new();
+
+        for key in self.children() {
+            if self.should_read(&key) {
+                let mut config: Config<Format> = self.configs.remove(&key).unwrap();
+            //                                   ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+            //                                   Here we retrieve ownership for the read functions
+
+                self.inherit_fallbacks(&key, &mut config);
+                let reinsert_config: Config<Format> = match config {
+                    Config::File(config_file) => Config::File(config_file.read().map_err(|err| err.within(&self.path))?),
+                    Config::Directory(config_dir) => Config::Directory(config_dir.read().map_err(|err| err.within(&self.path))?)
+                };
+
+                defaulted.add(&key, &reinsert_config);
                 self.configs.insert(key, reinsert_config);
             }
         }
 
-        if self.defaulted && self.options.write_if_defaulted {
+        self.defaulted = defaulted;
+
+        if self.defaulted.is_defaulted() && self.options.write_if_defaulted {
             self = self.write()?;
         }
         
         Ok(self)
     }
 
-    pub fn write(mut self) -> Result<Self, Error> {
+    pub fn write(mut self) -> Result<Self, ConfigError> {
         config::ensure(&self.path)?;
     //  ^^^^^^^^^^^^^^^ Calling write on a ConfigFile already ensures the directory exists.
     //                  However, if we call write on an empty ConfigDirectory,
     //                  we still want the directory to be made
 
         for key in self.children() {
-            // We should only write directory contents if recursive is enabled.
-            // Since the write functions require that we retrieve ownership,
-            // we want to be sure beforehand if we're supposed to write the config,
-            // which we figure out here
-            let config: &Config<Format> = self.configs.get(&key).unwrap();
-            let should_write: bool = if let Config::Directory(config_dir) = config {
-                if self.options.recursive {
-                    true
-                } else {
-                    config::ensure(&config_dir.path)?;
-                //  ^^^^^^^^^^^^^^^^^^^^^ If we're not going to write directory contents,
-                //                        we still want the directory to be made
-                    false
-                }
-            } else {
-                true
-            };
-
-            if should_write {
+            if self.should_write(&key)? {
                 let config: Config<Format> = self.configs.remove(&key).unwrap();
             //                               ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
             //                               Here we retrieve ownership for the write functions
 
                 match config {
                     Config::File(config_file) => {
-                        self.configs.insert(key, Config::File(config_file.write()?));
+                        self.configs.insert(key, Config::File(config_file.write().map_err(|err| err.within(&self.path))?));
                     },
                     Config::Directory(config_dir) => {
-                        self.configs.insert(key, Config::Directory(config_dir.write()?));
+                        self.configs.insert(key, Config::Directory(config_dir.write().map_err(|err| err.within(&self.path))?));
                     }
                 }
             }
@@ -233,14 +306,104 @@ impl<Format: format::Format + Sized + Clone> ConfigDirectory<Format> {
 
 }
 
+#[cfg(feature = "tokio")]
+impl<Format> ConfigDirectory<Format>
+where
+    Format: format::Format + Sized + Clone + Send + 'static,
+    Format::Content: Send,
+    Format::Defaults: Send
+{
+
+    /// Same as `read`, but reads the children concurrently without blocking the executor
+    pub async fn read_async(mut self) -> Result<Self, ConfigError> {
+        if self.options.read_new {
+            self = config::blocking(move || {
+                self.read_new()?;
+                Ok(self)
+            }).await?;
+        }
+
+        let mut tasks: JoinSet<(String, Result<Config<Format>, ConfigError>)> = JoinSet::new();
+
+        for key in self.children() {
+            if self.should_read(&key) {
+                let mut config: Config<Format> = self.configs.remove(&key).unwrap();
+
+                self.inherit_fallbacks(&key, &mut config);
+                tasks.spawn(async move {
+                    let read: Result<Config<Format>, ConfigError> = config.read_async().await;
+                    ( key, read )
+                });
+            }
+        }
+
+        let mut defaulted: DefaultedReport = DefaultedReport::new();
+
+        while let Some(( key, read )) = config::joined(tasks.join_next().await) {
+            let config: Config<Format> = read.map_err(|err| err.within(&self.path))?;
+
+            defaulted.add(&key, &config);
+            self.configs.insert(key, config);
+        }
+
+        self.defaulted = defaulted;
+
+        if self.defaulted.is_defaulted() && self.options.write_if_defaulted {
+            self = self.write_async().await?;
+        }
+
+        Ok(self)
+    }
+
+    /// Same as `write`, but writes the children concurrently without blocking the executor
+    pub async fn write_async(self) -> Result<Self, ConfigError> {
+        let ( mut config_dir, keys ) = config::blocking(move || {
+            config::ensure(&self.path)?;
+
+            let mut keys: Vec<String> = Vec::new();
+
+            for key in self.children() {
+                if self.should_write(&key)? {
+                    keys.push(key);
+                }
+            }
+
+            Ok(( self, keys ))
+        }).await?;
+        let mut tasks: JoinSet<(String, Result<Config<Format>, ConfigError>)> = JoinSet::new();
+
+        for key in keys {
+            let config: Config<Format> = config_dir.configs.remove(&key).unwrap();
+
+            tasks.spawn(async move {
+                let written: Result<Config<Format>, ConfigError> = config.write_async().await;
+                ( key, written )
+            });
+        }
+
+        while let Some(( key, written )) = config::joined(tasks.join_next().await) {
+            config_dir.configs.insert(key, written.map_err(|err| err.within(&config_dir.path))?);
+        }
+
+        Ok(config_dir)
+    }
+
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
     use crate::test::test_path::TestPath;
-    use crate::test::child_path::ChildPath;
+    use crate::test::child_path::{ self, ChildPath };
     use crate::config_file::ConfigFile;
+    use crate::format::Defaulted;
+    use crate::backup::Backups;
+    use crate::lock::Locking;
     use crate::formats::string_format::StringFormat;
+    use crate::formats::ini_format::{ IniFormat, Ini };
+    use crate::formats::env_file_format::{ EnvFileFormat, EnvFile };
+    use crate::formats::any_format::{ AnyFormat, FormatRegistry };
 
     #[test]
     fn new_directory() {
@@ -259,6 +422,278 @@ mod tests {
             .dir(ConfigDirectory::new(Path::new("test"), StringFormat::new()));
     }
 
+    #[test]
+    fn error_parents() {
+        let tp: TestPath = TestPath::new();
+        let p1: &Path = &tp.path;
+        let p2: &Path = &tp.child_path("test");
+        let p3: &Path = &child_path::child_path(p2, "test.txt");
+
+        fs::create_dir_all(p2).unwrap();
+        fs::write(p3, vec![0xff, 0xfe]).unwrap();
+
+        let err: ConfigError = ConfigDirectory::new(p1, StringFormat::new())
+            .dir(ConfigDirectory::new(p2, StringFormat::new())
+                .file(ConfigFile::new(Path::new("test.txt"), StringFormat::new())))
+            .opt(ConfigDirOpts {
+                recursive: true,
+                ..ConfigDirOpts::default()
+            })
+            .read()
+            .err()
+            .unwrap();
+
+        assert_eq!(err.path(), p3);
+        assert_eq!(err.operation(), Operation::Deserialize);
+        assert_eq!(err.parents(), &[p1.into(), p2.into()]);
+    }
+
+    #[test]
+    fn defaulted_report() {
+        let tp: TestPath = TestPath::new();
+        let p1: &Path = &tp.path;
+        let p2: &Path = &tp.child_path("test");
+        let mut e: EnvFile = EnvFile::new();
+
+        e.set("HOST", "localhost");
+        e.set("PORT", "80");
+        fs::create_dir_all(p2).unwrap();
+        fs::write(tp.child_path("a.env"), "HOST=localhost\nPORT=8080\n").unwrap();
+        fs::write(child_path::child_path(p2, "b.env"), "PORT=8080\n").unwrap();
+
+        let c: ConfigDirectory<EnvFileFormat> = ConfigDirectory::new(p1, EnvFileFormat::new().merge_defaults(true))
+            .file(ConfigFile::new(Path::new("a.env"), EnvFileFormat::new().merge_defaults(true)).def(e.clone()))
+            .file(ConfigFile::new(Path::new("c.env"), EnvFileFormat::new()).def(e.clone()))
+            .dir(ConfigDirectory::new(p2, EnvFileFormat::new())
+                .file(ConfigFile::new(Path::new("b.env"), EnvFileFormat::new().merge_defaults(true)).def(e)))
+            .opt(ConfigDirOpts {
+                recursive: true,
+                ..ConfigDirOpts::default()
+            })
+            .read()
+            .unwrap();
+
+        assert_eq!(c.defaulted.len(), 2);
+        assert_eq!(c.defaulted.get("a.env"), None);
+        assert_eq!(c.defaulted.get("c.env"), Some(&Defaulted::All));
+        assert_eq!(c.defaulted.keys(), vec![ "c.env", "test/b.env:HOST" ]);
+    }
+
+    #[test]
+    fn read_new_any_format() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.path;
+
+        fs::create_dir_all(p).unwrap();
+        fs::write(tp.child_path("server.ini"), "[server]\nport = 8080\n").unwrap();
+        fs::write(tp.child_path(".env"), "PORT=8080\n").unwrap();
+        fs::write(tp.child_path("notes.txt"), "Hello, world!").unwrap();
+
+        let c: ConfigDirectory<AnyFormat> = ConfigDirectory::new(p, AnyFormat::new(FormatRegistry::new()
+                .extension(&["ini"], IniFormat::new())
+                .extension(&["env"], EnvFileFormat::new())
+                .fallback(StringFormat::new())))
+            .opt(ConfigDirOpts {
+                read_new: true,
+                ..ConfigDirOpts::default()
+            })
+            .read()
+            .unwrap();
+
+        let content = |name: &str| match &c.configs[name] {
+            Config::File(config_file) => config_file.content.clone().unwrap(),
+            Config::Directory(_) => panic!("Expected a file")
+        };
+
+        assert_eq!(content("server.ini").downcast::<Ini>().unwrap().get("server", "port"), Some("8080"));
+        assert_eq!(content(".env").downcast::<EnvFile>().unwrap().get("PORT"), Some("8080"));
+        assert_eq!(content("notes.txt").downcast::<String>().unwrap(), "Hello, world!");
+    }
+
+    #[test]
+    fn read_new_skips_backups() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.path;
+        let o = || ConfigDirOpts {
+            read_new: true,
+            files: ConfigFileOpts {
+                backups: Backups::Numbered(2),
+                ..ConfigFileOpts::default()
+            },
+            ..ConfigDirOpts::default()
+        };
+
+        fs::create_dir_all(p).unwrap();
+        fs::write(tp.child_path("test.txt"), "Hello, world!").unwrap();
+
+        let c: ConfigDirectory<StringFormat> = ConfigDirectory::new(p, StringFormat::new())
+            .opt(o())
+            .read()
+            .unwrap()
+            .write()
+            .unwrap();
+
+        assert!(tp.child_path("test.txt.1.bak").is_file());
+        assert_eq!(c.configs.len(), 1);
+
+        let c: ConfigDirectory<StringFormat> = ConfigDirectory::new(p, StringFormat::new())
+            .opt(o())
+            .read()
+            .unwrap();
+
+        assert_eq!(c.configs.len(), 1);
+    }
+
+    #[test]
+    fn read_new_skips_backup_dir() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.path;
+        let o = || ConfigDirOpts {
+            read_new: true,
+            recursive: true,
+            files: ConfigFileOpts {
+                backups: Backups::Timestamped {
+                    dir: Path::new("backups").into(),
+                    keep: 2
+                },
+                ..ConfigFileOpts::default()
+            },
+            ..ConfigDirOpts::default()
+        };
+
+        fs::create_dir_all(p).unwrap();
+        fs::write(tp.child_path("test.txt"), "Hello, world!").unwrap();
+
+        ConfigDirectory::new(p, StringFormat::new())
+            .opt(o())
+            .read()
+            .unwrap()
+            .write()
+            .unwrap();
+
+        assert!(tp.child_path("backups").is_dir());
+
+        let c: ConfigDirectory<StringFormat> = ConfigDirectory::new(p, StringFormat::new())
+            .opt(o())
+            .read()
+            .unwrap();
+
+        assert_eq!(c.configs.keys().collect::<Vec<&String>>(), vec![ "test.txt" ]);
+    }
+
+    #[test]
+    fn read_new_passes_file_options() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.path;
+
+        fs::create_dir_all(tp.child_path("nested")).unwrap();
+        fs::write(tp.child_path("test.txt"), "Hello, world!").unwrap();
+        fs::write(tp.child_path("nested/test.txt"), "Hello, world!").unwrap();
+
+        ConfigDirectory::new(p, StringFormat::new())
+            .opt(ConfigDirOpts {
+                read_new: true,
+                recursive: true,
+                files: ConfigFileOpts {
+                    lock: Locking::Blocking,
+                    ..ConfigFileOpts::default()
+                },
+                ..ConfigDirOpts::default()
+            })
+            .read()
+            .unwrap();
+
+        assert!(tp.child_path(".test.txt.lock").is_file());
+        assert!(tp.child_path("nested/.test.txt.lock").is_file());
+    }
+
+    #[test]
+    fn read_fallbacks() {
+        let tp: TestPath = TestPath::new();
+        let home: &Path = &tp.child_path("home");
+        let system: &Path = &tp.child_path("system");
+
+        fs::create_dir_all(home).unwrap();
+        fs::create_dir_all(system).unwrap();
+        fs::write(child_path::child_path(home, "a.txt"), "home").unwrap();
+        fs::write(child_path::child_path(system, "a.txt"), "system").unwrap();
+        fs::write(child_path::child_path(system, "b.txt"), "system").unwrap();
+
+        let mut c: ConfigDirectory<StringFormat> = ConfigDirectory::new(home, StringFormat::new())
+            .fallback(system)
+            .opt(ConfigDirOpts {
+                read_new: true,
+                ..ConfigDirOpts::default()
+            })
+            .read()
+            .unwrap();
+
+        let content = |c: &ConfigDirectory<StringFormat>, key: &str| match c.configs.get(key) {
+            Some(Config::File(config_file)) => config_file.content.clone(),
+            _ => None
+        };
+
+        assert_eq!(content(&c, "a.txt"), Some(String::from("home")));
+        assert_eq!(content(&c, "b.txt"), Some(String::from("system")));
+
+        if let Some(Config::File(config_file)) = c.configs.get_mut("b.txt") {
+            config_file.content = Some(String::from("user"));
+        }
+
+        c.write().unwrap();
+
+        assert_eq!(fs::read_to_string(child_path::child_path(home, "b.txt")).unwrap(), "user");
+        assert_eq!(fs::read_to_string(child_path::child_path(system, "b.txt")).unwrap(), "system");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn read_async() {
+        let tp: TestPath = TestPath::new();
+        let p1: &Path = &tp.path;
+        let p2: &Path = &tp.child_path("test");
+
+        fs::create_dir_all(p2).unwrap();
+        fs::write(tp.child_path("a.txt"), "Hello, world!").unwrap();
+
+        let c: ConfigDirectory<StringFormat> = ConfigDirectory::new(p1, StringFormat::new())
+            .file(ConfigFile::new(Path::new("c.txt"), StringFormat::new()).def(String::from("Goodbye, world!")))
+            .opt(ConfigDirOpts {
+                write_if_defaulted: true,
+                read_new: true,
+                recursive: true,
+                ..ConfigDirOpts::default()
+            })
+            .read_async()
+            .await
+            .unwrap();
+
+        assert!(c.defaulted.is_defaulted());
+        assert_eq!(c.configs.len(), 3);
+        assert_eq!(fs::read_to_string(tp.child_path("c.txt")).unwrap(), "Goodbye, world!");
+        assert!(matches!(c.configs["test"], Config::Directory(_)));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn read_async_error_parents() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.path;
+
+        fs::create_dir_all(p).unwrap();
+        fs::write(tp.child_path("test.txt"), vec![0xff, 0xfe]).unwrap();
+
+        let err: ConfigError = ConfigDirectory::new(p, StringFormat::new())
+            .file(ConfigFile::new(Path::new("test.txt"), StringFormat::new()))
+            .read_async()
+            .await
+            .err()
+            .unwrap();
+
+        assert_eq!(err.operation(), Operation::Deserialize);
+        assert_eq!(err.parents(), &[p.into()]);
+    }
+
     #[test]
     fn ensure_parent() {
         let tp: TestPath = TestPath::new();
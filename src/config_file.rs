@@ -1,32 +1,97 @@
-use std::path::Path;
-use std::io::Error;
+use std::path::{ Path, PathBuf };
 use std::fs;
 
-use crate::format;
+use crate::format::{ self, Defaulted };
 use crate::config;
-
+use crate::backup::{ self, Backups };
+use crate::lock::{ self, Locking, Lock };
+use crate::value::Value;
+use crate::env::{ EnvOverrides, Guessed };
+use crate::args::ArgOverrides;
+use crate::xdg::BaseDir;
+use crate::interpolate;
+use crate::error::{ ConfigError, Operation };
+
+#[derive(Clone)]
 pub struct ConfigFileOpts {
-    pub write_if_defaulted: bool
+    pub write_if_defaulted: bool,
+    /// Write to a temporary file and rename it over the config,
+    /// so a crash or full disk mid-write never leaves a truncated config behind. Enabled by default
+    pub atomic: bool,
+    /// Copies of the file to keep before it's overwritten, none by default
+    pub backups: Backups,
+    /// Advisory locking of the file, shared while reading and exclusive while writing. None by default
+    pub lock: Locking
 }
 
 impl Default for ConfigFileOpts {
 
     fn default() -> Self {
-        ConfigFileOpts {
-            write_if_defaulted: false
+        Self {
+            write_if_defaulted: false,
+            atomic: true,
+            backups: Backups::None,
+            lock: Locking::None
         }
     }
-    
+
 }
 
+/// Applies environment variable and command line overrides and interpolation to freshly read content.
+/// Set along with the overrides where the format is known to be a `StructuredFormat`
+type Overlay<Format> = fn(&mut ConfigFile<Format>) -> Result<(), ConfigError>;
+
+/// Puts the values of the file back in place of the overrides and the templates back in place of the values they expanded to
+/// before the content is written, `None` if there's nothing to put back
+type RevertOverlay<Format> = fn(&mut ConfigFile<Format>) -> Result<Option<<Format as format::Format>::Content>, ConfigError>;
+
 pub struct ConfigFile<Format: format::Format + Sized + Clone> {
     pub path: Box<Path>,
     pub content: Option<Format::Content>,
-    pub defaulted: bool,
+    /// What of the content was filled in from defaults by the last read
+    pub defaulted: Defaulted,
+    /// Paths read from, in order, when there's no file at `path`. Writes always go to `path`
+    pub fallbacks: Vec<Box<Path>>,
 
     format: Format,
     options: ConfigFileOpts,
-    defaults: Option<Format::Defaults>
+    defaults: Option<Format::Defaults>,
+    env: Option<EnvOverrides>,
+    args: Option<ArgOverrides>,
+    interpolate: bool,
+    /// Paths of the values expanded by the last read, along with their templates and what they expanded to
+    templates: Vec<( String, Value, Value )>,
+    /// Paths of the values overridden by the last read, along with the values of the file, if it had them, and what they were overridden with
+    overrides: Vec<( String, Option<Value>, Value )>,
+    overlay: Option<Overlay<Format>>,
+    revert_overlay: Option<RevertOverlay<Format>>
+}
+
+impl<Format: format::Format + Sized + Clone> Clone for ConfigFile<Format>
+where
+    Format::Content: Clone
+{
+
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            content: self.content.clone(),
+            defaulted: self.defaulted.clone(),
+            fallbacks: self.fallbacks.clone(),
+
+            format: self.format.clone(),
+            options: self.options.clone(),
+            defaults: self.defaults.clone(),
+            env: self.env.clone(),
+            args: self.args.clone(),
+            interpolate: self.interpolate,
+            templates: self.templates.clone(),
+            overrides: self.overrides.clone(),
+            overlay: self.overlay,
+            revert_overlay: self.revert_overlay
+        }
+    }
+
 }
 
 impl<Format: format::Format + Sized + Clone> ConfigFile<Format> {
@@ -36,13 +101,53 @@ impl<Format: format::Format + Sized + Clone> ConfigFile<Format> {
             path: path.to_path_buf().into_boxed_path(),
             options: ConfigFileOpts::default(),
             content: None,
-            defaulted: false,
+            defaulted: Defaulted::None,
+            fallbacks: Vec::new(),
 
             format,
-            defaults: None
+            defaults: None,
+            env: None,
+            args: None,
+            interpolate: false,
+            templates: Vec::new(),
+            overrides: Vec::new(),
+            overlay: None,
+            revert_overlay: None
         }
     }
 
+    /// Config `file_name` of `app_name` in the XDG config directories,
+    /// read from the first of `$XDG_CONFIG_HOME` and `$XDG_CONFIG_DIRS` that has it and written to `$XDG_CONFIG_HOME`.
+    /// `None` if the user's home can't be found
+    pub fn xdg(app_name: &str, file_name: &str, format: Format) -> Option<Self> {
+        Self::xdg_in(BaseDir::Config, app_name, file_name, format)
+    }
+
+    /// Same as `xdg`, in the XDG data directories
+    pub fn xdg_data(app_name: &str, file_name: &str, format: Format) -> Option<Self> {
+        Self::xdg_in(BaseDir::Data, app_name, file_name, format)
+    }
+
+    /// Same as `xdg`, in the XDG state directory
+    pub fn xdg_state(app_name: &str, file_name: &str, format: Format) -> Option<Self> {
+        Self::xdg_in(BaseDir::State, app_name, file_name, format)
+    }
+
+    fn xdg_in(base_dir: BaseDir, app_name: &str, file_name: &str, format: Format) -> Option<Self> {
+        let ( path, fallbacks ) = base_dir.paths(&Path::new(app_name).join(file_name))?;
+        let mut config_file: Self = Self::new(&path, format);
+
+        config_file.fallbacks = fallbacks.into_iter().map(PathBuf::into_boxed_path).collect();
+
+        Some(config_file)
+    }
+
+    /// Adds a path to read from when there's no file at `path`, after the fallbacks added before it
+    pub fn fallback(mut self, path: &Path) -> Self {
+        self.fallbacks.push(path.into());
+        self
+    }
+
     pub fn def(mut self, defaults: Format::Defaults) -> Self {
         self.defaults = Some(defaults);
         self
@@ -53,9 +158,22 @@ impl<Format: format::Format + Sized + Clone> ConfigFile<Format> {
         self
     }
 
-    pub fn read(mut self) -> Result<Self, Error> {
-        let bytes: Vec<u8> = if self.path.is_file() {
-            fs::read(&self.path)?
+    /// Path to read from, `path` or the first of the fallbacks that exists
+    fn read_path(&self) -> Box<Path> {
+        if self.path.is_file() {
+            return self.path.clone();
+        }
+
+        self.fallbacks.iter().find(|fallback| fallback.is_file()).unwrap_or(&self.path).clone()
+    }
+
+    pub fn read(mut self) -> Result<Self, ConfigError> {
+        let path: Box<Path> = self.read_path();
+
+        self.format.select(&path);
+
+        let bytes: Vec<u8> = if path.is_file() {
+            self.read_bytes(&path)?
         } else {
             Vec::new()
         };
@@ -63,38 +181,260 @@ impl<Format: format::Format + Sized + Clone> ConfigFile<Format> {
             Some(__defaults) => Some(__defaults),
             None => None
         };
-        let deserialized: format::Deserialized<Format::Content> = self.format.deserialize(bytes, defaults);
+        let deserialized: format::Deserialized<Format::Content> = self.format.deserialize(bytes, defaults)
+            .map_err(|err| ConfigError::deserialize(&path, err))?;
 
         self.content = Some(deserialized.0);
         self.defaulted = deserialized.1;
+        self.templates.clear();
+        self.overrides.clear();
 
-        if self.defaulted && self.options.write_if_defaulted {
+        if self.defaulted.is_defaulted() && self.options.write_if_defaulted {
             self = self.write()?;
         }
+
+        if let Some(overlay) = self.overlay {
+            overlay(&mut self)?;
+        }
         
         Ok(self)
     }
 
-    pub fn write(mut self) -> Result<Self, Error> {
+    fn read_bytes(&self, path: &Path) -> Result<Vec<u8>, ConfigError> {
+        let _lock: Option<Lock> = lock::lock(path, self.options.lock, false)
+            .map_err(|err| ConfigError::io(path, Operation::Lock, err))?;
+
+        fs::read(path).map_err(|err| ConfigError::io(path, Operation::Read, err))
+    }
+
+    pub fn write(mut self) -> Result<Self, ConfigError> {
         let parent: Option<&Path> = self.path.parent();
 
         if let Some(parent_path) = parent {
             config::ensure(parent_path)?;
         }
 
-        let content: Option<&Format::Content> = match &self.content {
-            Some(content) => Some(content),
+        self.format.select(&self.path);
+
+        let restored: Option<Format::Content> = match self.revert_overlay {
+            Some(revert_overlay) => revert_overlay(&mut self)?,
             None => None
         };
-        let deserialized: Vec<u8> = self.format.serialize(content);
+        let content: Option<&Format::Content> = match &restored {
+            Some(__content) => Some(__content),
+            None => self.content.as_ref()
+        };
+        let serialized: Vec<u8> = self.format.serialize(content)
+            .map_err(|err| ConfigError::serialize(&self.path, err))?;
 
-        fs::write(&self.path, deserialized)?;
+        self.replace(&serialized)?;
 
         Ok(self)
     }
+
+    /// Backups of the file made with the backup policy, newest first
+    pub fn backups(&self) -> Result<Vec<Box<Path>>, ConfigError> {
+        backup::list(&self.path, &self.options.backups).map_err(|err| ConfigError::io(&self.path, Operation::ReadDir, err))
+    }
+
+    /// Replaces the file with a backup from `backups` and reads it.
+    ///
+    /// The file being replaced is backed up first, so restoring can be undone
+    pub fn restore(self, backup_path: &Path) -> Result<Self, ConfigError> {
+        let bytes: Vec<u8> = fs::read(backup_path).map_err(|err| ConfigError::io(backup_path, Operation::Read, err))?;
+
+        if let Some(parent_path) = self.path.parent() {
+            config::ensure(parent_path)?;
+        }
+
+        self.replace(&bytes)?;
+        self.read()
+    }
+
+    /// Backs up the file if there is one, then overwrites it with `bytes`
+    fn replace(&self, bytes: &[u8]) -> Result<(), ConfigError> {
+        let _lock: Option<Lock> = lock::lock(&self.path, self.options.lock, true)
+            .map_err(|err| ConfigError::io(&self.path, Operation::Lock, err))?;
+
+        if self.path.is_file() {
+            backup::backup(&self.path, &self.options.backups).map_err(|err| ConfigError::io(&self.path, Operation::Backup, err))?;
+        }
+
+        if self.options.atomic {
+            config::write_atomic(&self.path, bytes)
+        } else {
+            fs::write(&self.path, bytes)
+        }.map_err(|err| ConfigError::io(&self.path, Operation::Write, err))
+    }
     
 }
 
+impl<Format: format::StructuredFormat + Sized + Clone> ConfigFile<Format> {
+
+    /// Overrides values with environment variables after every read, see `EnvOverrides`.
+    ///
+    /// Overridden values aren't written, `write` keeps the values of the file for them unless they were changed after the read
+    pub fn env(mut self, env: EnvOverrides) -> Self {
+        self.env = Some(env);
+        self.overlay = Some(overlay::<Format>);
+        self.revert_overlay = Some(revert_overlay::<Format>);
+        self
+    }
+
+    /// Overrides values with command line arguments after every read, after environment variables, see `ArgOverrides`.
+    ///
+    /// Keys the content doesn't have and values of the wrong type fail the read.
+    /// Like environment variables, overridden values aren't written.
+    ///
+    /// A file given with `--config` replaces `path` and the fallbacks
+    pub fn args(mut self, args: ArgOverrides) -> Self {
+        if let Some(config) = args.config() {
+            self.path = config.into();
+            self.fallbacks.clear();
+        }
+
+        self.args = Some(args);
+        self.overlay = Some(overlay::<Format>);
+        self.revert_overlay = Some(revert_overlay::<Format>);
+        self
+    }
+
+    /// Expands `${env:VAR}`, `${file:path}` and `${self:key.path}` inside string values after every read,
+    /// after environment variable and command line overrides, see `interpolate::interpolate`.
+    ///
+    /// The templates are kept, so `write` writes them back in place of the values they expanded to, unless those values were changed
+    pub fn interpolate(mut self, interpolate: bool) -> Self {
+        self.interpolate = interpolate;
+        self.overlay = Some(overlay::<Format>);
+        self.revert_overlay = Some(revert_overlay::<Format>);
+        self
+    }
+
+    /// Template the value at `path` was expanded from by the last read, `None` if it wasn't expanded
+    pub fn template(&self, path: &str) -> Option<&Value> {
+        self.templates.iter().find(|( key_path, _, _ )| key_path == path).map(|( _, template, _ )| template)
+    }
+
+    /// Reads the file as a `Value` tree without deserializing it as the content type, so it may hold only some of the keys.
+    /// `None` if there's no file
+    pub fn read_value(&mut self) -> Result<Option<Value>, ConfigError> {
+        let path: Box<Path> = self.read_path();
+
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        self.format.select(&path);
+
+        let bytes: Vec<u8> = self.read_bytes(&path)?;
+
+        self.format.parse(bytes).map(Some).map_err(|err| ConfigError::deserialize(&path, err))
+    }
+
+}
+
+fn overlay<Format: format::StructuredFormat + Sized + Clone>(config_file: &mut ConfigFile<Format>) -> Result<(), ConfigError> {
+    let path: &Path = &config_file.path;
+    let content: &Format::Content = match &config_file.content {
+        Some(content) => content,
+        None => return Ok(())
+    };
+    let mut value: Value = config_file.format.to_value(content).map_err(|err| ConfigError::serialize(path, err))?;
+    let file_value: Value = value.clone();
+    let mut overridden: Vec<String> = Vec::new();
+    let mut guessed: Guessed = Guessed::default();
+
+    if let Some(env) = &config_file.env {
+        overridden.extend(env.apply_guessing(&mut value, &mut guessed).map_err(|err| ConfigError::deserialize(path, err))?);
+    }
+
+    if let Some(args) = &config_file.args {
+        overridden.extend(args.apply_guessing(&mut value, &mut guessed).map_err(|err| ConfigError::deserialize(path, err))?);
+    }
+
+    let mut overrides: Vec<String> = overridden.clone();
+
+    overrides.sort();
+    overrides.dedup();
+
+    if config_file.interpolate {
+        for ( key_path, template ) in interpolate::interpolate(&mut value).map_err(|err| ConfigError::deserialize(path, err))? {
+            let expanded: Value = value.get(&key_path).cloned().unwrap_or(Value::Null);
+
+            overridden.push(key_path.clone());
+            config_file.templates.push(( key_path, template, expanded ));
+        }
+    }
+
+    // Left as it is when nothing is overridden, converting back and forth may lose details of the content
+    if !overridden.is_empty() {
+        let content: Format::Content = guessed.to_content(&mut config_file.format, value).map_err(|err| ConfigError::deserialize(path, err))?;
+
+        if !overrides.is_empty() {
+            // As the content holds them, guesses may have been read as strings
+            let overridden_value: Value = config_file.format.to_value(&content).map_err(|err| ConfigError::serialize(path, err))?;
+
+            for key_path in overrides {
+                let overridden: Value = overridden_value.get(&key_path).cloned().unwrap_or(Value::Null);
+
+                config_file.overrides.push(( key_path.clone(), file_value.get(&key_path).cloned(), overridden ));
+            }
+        }
+
+        config_file.content = Some(content);
+    }
+
+    Ok(())
+}
+
+fn revert_overlay<Format: format::StructuredFormat + Sized + Clone>(config_file: &mut ConfigFile<Format>) -> Result<Option<Format::Content>, ConfigError> {
+    let path: &Path = &config_file.path;
+    let content: &Format::Content = match &config_file.content {
+        Some(content) if !config_file.templates.is_empty() || !config_file.overrides.is_empty() => content,
+        _ => return Ok(None)
+    };
+    let mut value: Value = config_file.format.to_value(content).map_err(|err| ConfigError::serialize(path, err))?;
+
+    for ( key_path, file_value, overridden ) in &config_file.overrides {
+        if value.get(key_path) == Some(overridden) {
+            match file_value {
+                Some(__file_value) => value.set(key_path, __file_value.clone()),
+                None => {
+                    value.remove(key_path);
+                }
+            }
+        }
+    }
+
+    for ( key_path, template, expanded ) in &config_file.templates {
+        if value.get(key_path) == Some(expanded) {
+            value.set(key_path, template.clone());
+        }
+    }
+
+    config_file.format.to_content(value).map(Some).map_err(|err| ConfigError::serialize(path, err))
+}
+
+#[cfg(feature = "tokio")]
+impl<Format> ConfigFile<Format>
+where
+    Format: format::Format + Sized + Clone + Send + 'static,
+    Format::Content: Send,
+    Format::Defaults: Send
+{
+
+    /// Same as `read`, run on tokio's blocking thread pool so it doesn't block the executor
+    pub async fn read_async(self) -> Result<Self, ConfigError> {
+        config::blocking(move || self.read()).await
+    }
+
+    /// Same as `write`, run on tokio's blocking thread pool so it doesn't block the executor
+    pub async fn write_async(self) -> Result<Self, ConfigError> {
+        config::blocking(move || self.write()).await
+    }
+
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -103,6 +443,7 @@ mod tests {
     use crate::test::test_file::TestFile;
     use crate::test::child_path::ChildPath;
     use crate::formats::string_format::StringFormat;
+    use crate::formats::env_file_format::{ EnvFileFormat, EnvFile };
 
     #[test]
     fn new_config() {
@@ -156,7 +497,7 @@ mod tests {
             .read()
             .unwrap();
         
-        assert_eq!(c.defaulted, true);
+        assert_eq!(c.defaulted, Defaulted::All);
     }
 
     #[test]
@@ -170,7 +511,261 @@ mod tests {
         f.write(&s);
         c = c.read().unwrap();
 
-        assert_eq!(c.defaulted, false);
+        assert!(!c.defaulted.is_defaulted());
+    }
+
+    #[test]
+    fn write_if_keys_defaulted() {
+        let p: &Path = &TestPath::new().path;
+        let f: TestFile = TestFile::new(p);
+        let mut e: EnvFile = EnvFile::new();
+
+        e.set("HOST", "localhost");
+        e.set("PORT", "80");
+        f.write(&String::from("PORT=8080\n"));
+
+        let c: ConfigFile<EnvFileFormat> = ConfigFile::new(p, EnvFileFormat::new().merge_defaults(true))
+            .def(e)
+            .opt(ConfigFileOpts {
+                write_if_defaulted: true,
+                ..Default::default()
+            })
+            .read()
+            .unwrap();
+
+        assert_eq!(c.defaulted, Defaulted::Keys(vec![ String::from("HOST") ]));
+        assert_eq!(f.read(), "PORT=8080\nHOST=localhost\n");
+    }
+
+    #[test]
+    fn read_fallback() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.child_path("user.txt");
+        let f: &Path = &tp.child_path("system.txt");
+
+        fs::create_dir_all(&tp.path).unwrap();
+        fs::write(f, "Hello, world!").unwrap();
+
+        let mut c: ConfigFile<StringFormat> = ConfigFile::new(p, StringFormat::new())
+            .fallback(&tp.child_path("missing.txt"))
+            .fallback(f)
+            .read()
+            .unwrap();
+
+        assert_eq!(c.content.as_deref(), Some("Hello, world!"));
+
+        c.content = Some(String::from("Hello, user!"));
+        c = c.write().unwrap().read().unwrap();
+
+        assert_eq!(c.content.as_deref(), Some("Hello, user!"));
+        assert_eq!(fs::read_to_string(f).unwrap(), "Hello, world!");
+    }
+
+    #[test]
+    fn env_overrides() {
+        let p: &Path = &TestPath::new().path;
+        let f: TestFile = TestFile::new(p);
+
+        f.write(&String::from("HOST=localhost\nPORT=80\n"));
+        std::env::set_var("CONFIG_FILE_ENV__PORT", "8080");
+
+        let c: ConfigFile<EnvFileFormat> = ConfigFile::new(p, EnvFileFormat::new())
+            .env(EnvOverrides::new("CONFIG_FILE_ENV"))
+            .read()
+            .unwrap();
+        let content: EnvFile = c.content.unwrap();
+
+        assert_eq!(content.get("HOST"), Some("localhost"));
+        assert_eq!(content.get("PORT"), Some("8080"));
+    }
+
+    #[test]
+    fn overrides_not_written() {
+        let p: &Path = &TestPath::new().path;
+        let f: TestFile = TestFile::new(p);
+
+        f.write(&String::from("HOST=localhost\nPORT=80\nDEBUG=0\n"));
+        std::env::set_var("CONFIG_FILE_WRITE__PORT", "8080");
+        std::env::set_var("CONFIG_FILE_WRITE__SECRET", "hunter2");
+
+        let mut c: ConfigFile<EnvFileFormat> = ConfigFile::new(p, EnvFileFormat::new())
+            .env(EnvOverrides::new("CONFIG_FILE_WRITE"))
+            .args(ArgOverrides::new().set("DEBUG", "1"))
+            .read()
+            .unwrap();
+        let content: &mut EnvFile = c.content.as_mut().unwrap();
+
+        assert_eq!(content.get("secret"), Some("hunter2"));
+        content.set("HOST", "db.internal");
+
+        c.write().unwrap();
+
+        assert_eq!(f.read(), "HOST=db.internal\nPORT=80\nDEBUG=0\n");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn env_overrides_guessed_as_strings() {
+        use crate::formats::json_format::JsonFormat;
+        use serde::{ Serialize, Deserialize };
+
+        #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+        struct Settings {
+            password: Option<String>,
+            port: Option<u16>
+        }
+
+        let p: &Path = &TestPath::new().path;
+        let f: TestFile = TestFile::new(p);
+
+        f.write(&String::from(r#"{ "password": null, "port": 80 }"#));
+        std::env::set_var("CONFIG_FILE_GUESSED__PASSWORD", "01234");
+        std::env::set_var("CONFIG_FILE_GUESSED__PORT", "8080");
+
+        let c: ConfigFile<JsonFormat<Settings>> = ConfigFile::new(p, JsonFormat::new())
+            .env(EnvOverrides::new("CONFIG_FILE_GUESSED"))
+            .read()
+            .unwrap();
+
+        assert_eq!(c.content.unwrap(), Settings {
+            password: Some(String::from("01234")),
+            port: Some(8080)
+        });
+    }
+
+    #[cfg(all(feature = "json", feature = "msgpack"))]
+    #[test]
+    fn swap_json_for_msgpack() {
+        use crate::formats::json_format::JsonFormat;
+        use crate::formats::msgpack_format::MsgPackFormat;
+        use serde::{ Serialize, Deserialize };
+
+        #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+        struct Settings {
+            name: String,
+            port: u16
+        }
+
+        fn read<Format>(p: &Path, format: Format) -> Settings
+        where
+            Format: format::StructuredFormat<Content = Settings, Defaults = Settings> + Clone
+        {
+            ConfigFile::new(p, format.clone())
+                .def(Settings { name: String::from("web"), port: 80 })
+                .read()
+                .unwrap()
+                .write()
+                .unwrap();
+
+            ConfigFile::new(p, format)
+                .env(EnvOverrides::new("CONFIG_FILE_SWAP"))
+                .read()
+                .unwrap()
+                .content
+                .unwrap()
+        }
+
+        let tp: TestPath = TestPath::new();
+
+        fs::create_dir_all(&tp.path).unwrap();
+        std::env::set_var("CONFIG_FILE_SWAP__PORT", "8080");
+
+        assert_eq!(read(&tp.child_path("settings.json"), JsonFormat::new()), Settings { name: String::from("web"), port: 8080 });
+        assert_eq!(read(&tp.child_path("settings.msgpack"), MsgPackFormat::new()), Settings { name: String::from("web"), port: 8080 });
+    }
+
+    #[test]
+    fn arg_overrides() {
+        let p: &Path = &TestPath::new().path;
+        let f: TestFile = TestFile::new(p);
+        let a: ArgOverrides = ArgOverrides::parse(vec![ "--set", "PORT=8080" ]).unwrap();
+
+        f.write(&String::from("HOST=localhost\nPORT=80\n"));
+
+        let c: ConfigFile<EnvFileFormat> = ConfigFile::new(p, EnvFileFormat::new())
+            .args(a)
+            .read()
+            .unwrap();
+
+        assert_eq!(c.content.unwrap().get("PORT"), Some("8080"));
+
+        let err: ConfigError = ConfigFile::new(p, EnvFileFormat::new())
+            .args(ArgOverrides::new().set("USER", "admin"))
+            .read()
+            .err()
+            .unwrap();
+
+        assert_eq!(err.path(), p);
+        assert_eq!(err.operation(), Operation::Deserialize);
+    }
+
+    #[test]
+    fn arg_config() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.child_path("default.env");
+        let other: &Path = &tp.child_path("other.env");
+
+        fs::create_dir_all(&tp.path).unwrap();
+        fs::write(p, "HOST=localhost\n").unwrap();
+        fs::write(other, "HOST=db.internal\n").unwrap();
+
+        let a: ArgOverrides = ArgOverrides::parse(vec![ "--config", &other.display().to_string() ]).unwrap();
+        let c: ConfigFile<EnvFileFormat> = ConfigFile::new(p, EnvFileFormat::new())
+            .fallback(p)
+            .args(a)
+            .read()
+            .unwrap();
+
+        assert_eq!(&*c.path, other);
+        assert_eq!(c.content.unwrap().get("HOST"), Some("db.internal"));
+    }
+
+    #[test]
+    fn interpolate_keeps_templates() {
+        let p: &Path = &TestPath::new().path;
+        let f: TestFile = TestFile::new(p);
+
+        f.write(&String::from("DIR=/srv\nDATA=${self:DIR}/data\nLOGS=\"${self:DIR}/logs\"\n"));
+
+        let mut c: ConfigFile<EnvFileFormat> = ConfigFile::new(p, EnvFileFormat::new())
+            .interpolate(true)
+            .read()
+            .unwrap();
+        let content: &mut EnvFile = c.content.as_mut().unwrap();
+
+        assert_eq!(content.get("DATA"), Some("/srv/data"));
+        content.set("LOGS", "/var/log");
+        assert_eq!(c.template("DATA"), Some(&Value::from("${self:DIR}/data")));
+
+        c.write().unwrap();
+
+        assert_eq!(f.read(), "DIR=/srv\nDATA=${self:DIR}/data\nLOGS=/var/log\n");
+    }
+
+    #[test]
+    fn deserialize_error() {
+        let p: &Path = &TestPath::new().path;
+
+        fs::write(p, vec![0xff, 0xfe]).unwrap();
+
+        let err: ConfigError = ConfigFile::new(p, StringFormat::new()).read().err().unwrap();
+
+        assert_eq!(err.path(), p);
+        assert_eq!(err.operation(), Operation::Deserialize);
+    }
+
+    #[test]
+    fn write_error() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.path;
+
+        fs::create_dir_all(p).unwrap();
+
+        let err: ConfigError = ConfigFile::new(p, StringFormat::new()).write().err().unwrap();
+
+        assert_eq!(err.path(), p);
+        assert_eq!(err.operation(), Operation::Write);
+        assert!(err.io_error().is_some());
     }
 
     #[test]
@@ -182,7 +777,8 @@ mod tests {
         ConfigFile::new(p, StringFormat::new())
             .def(s.clone())
             .opt(ConfigFileOpts {
-                write_if_defaulted: true
+                write_if_defaulted: true,
+                ..Default::default()
             })
             .read()
             .unwrap();
@@ -190,6 +786,96 @@ mod tests {
         assert_eq!(f.read(), s);
     }
 
+    #[test]
+    fn write_not_atomic() {
+        let p: &Path = &TestPath::new().path;
+        let f: TestFile = TestFile::new(p);
+        let s: String = String::from("Hello, world!");
+        let mut c: ConfigFile<StringFormat> = ConfigFile::new(p, StringFormat::new())
+            .opt(ConfigFileOpts {
+                atomic: false,
+                ..Default::default()
+            });
+
+        c.content = Some(s.clone());
+        c.write().unwrap();
+
+        assert_eq!(f.read(), s);
+    }
+
+    #[test]
+    fn backups_restore() {
+        let p: &Path = &TestPath::new().path;
+        let f: TestFile = TestFile::new(p);
+        let mut c: ConfigFile<StringFormat> = ConfigFile::new(p, StringFormat::new())
+            .opt(ConfigFileOpts {
+                backups: Backups::Numbered(3),
+                ..Default::default()
+            });
+
+        for s in [ "1", "2", "3" ] {
+            c.content = Some(String::from(s));
+            c = c.write().unwrap();
+        }
+
+        let b: Vec<Box<Path>> = c.backups().unwrap();
+        assert_eq!(b.len(), 2);
+
+        c = c.restore(&b[1]).unwrap();
+
+        assert_eq!(c.content.clone().unwrap(), "1");
+        assert_eq!(f.read(), "1");
+        assert_eq!(c.backups().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn lock_error() {
+        let p: &Path = &TestPath::new().path;
+        let s: String = String::from("Hello, world!");
+        let mut c: ConfigFile<StringFormat> = ConfigFile::new(p, StringFormat::new())
+            .opt(ConfigFileOpts {
+                lock: Locking::Try,
+                ..Default::default()
+            });
+
+        c.content = Some(s.clone());
+        c = c.write().unwrap();
+
+        let _l: Option<Lock> = lock::lock(p, Locking::Blocking, true).unwrap();
+        let err: ConfigError = c.read().err().unwrap();
+
+        assert_eq!(err.operation(), Operation::Lock);
+        assert_eq!(err.io_error().unwrap().kind(), std::io::ErrorKind::WouldBlock);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn read_write_async() {
+        let p: &Path = &TestPath::new().path;
+        let f: TestFile = TestFile::new(p);
+        let s: String = String::from("Hello, world!");
+
+        let c: ConfigFile<StringFormat> = ConfigFile::new(p, StringFormat::new())
+            .def(s.clone())
+            .opt(ConfigFileOpts {
+                write_if_defaulted: true,
+                ..Default::default()
+            })
+            .read_async()
+            .await
+            .unwrap();
+
+        assert!(c.defaulted.is_defaulted());
+        assert_eq!(f.read(), s);
+
+        let mut c: ConfigFile<StringFormat> = c;
+
+        c.content = Some(String::from("Goodbye, world!"));
+        c.write_async().await.unwrap();
+
+        assert_eq!(f.read(), "Goodbye, world!");
+    }
+
     #[test]
     fn ensure_parent() {
         let tp: TestPath = TestPath::new();
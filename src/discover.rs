@@ -0,0 +1,140 @@
+use std::path::{ Path, PathBuf };
+use std::fs;
+
+use crate::format;
+use crate::config_file::ConfigFile;
+
+/// Entries marking the root of a repository
+const REPO_MARKERS: [&str; 3] = [ ".git", ".hg", ".svn" ];
+
+/// Finds a project config in a directory or its nearest ancestor, the way git finds `.git`.
+///
+/// The search stops at the root of the repository it started in and where the file system changes,
+/// so a config of another project or of the machine isn't picked up by accident
+#[derive(Clone, Debug)]
+pub struct Discover {
+    names: Vec<String>,
+    repo_boundary: bool,
+    filesystem_boundary: bool
+}
+
+impl Discover {
+
+    /// Looks for any of `names` in each directory, in order of preference.
+    /// The first is also the name of the file when none is found
+    pub fn new(names: &[&str]) -> Self {
+        Self {
+            names: names.iter().map(|name| String::from(*name)).collect(),
+            repo_boundary: true,
+            filesystem_boundary: true
+        }
+    }
+
+    /// Whether to stop at a directory with a `.git`, `.hg` or `.svn`, enabled by default
+    pub fn repo_boundary(mut self, repo_boundary: bool) -> Self {
+        self.repo_boundary = repo_boundary;
+        self
+    }
+
+    /// Whether to stop before crossing into another file system, enabled by default. Only checked on unix
+    pub fn filesystem_boundary(mut self, filesystem_boundary: bool) -> Self {
+        self.filesystem_boundary = filesystem_boundary;
+        self
+    }
+
+    fn is_repo_root(dir: &Path) -> bool {
+        REPO_MARKERS.iter().any(|marker| dir.join(marker).exists())
+    }
+
+    #[cfg(unix)]
+    fn same_filesystem(dir: &Path, parent: &Path) -> bool {
+        use std::os::unix::fs::MetadataExt;
+
+        match ( fs::metadata(dir), fs::metadata(parent) ) {
+            ( Ok(metadata), Ok(parent_metadata) ) => metadata.dev() == parent_metadata.dev(),
+            _ => false
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn same_filesystem(_dir: &Path, _parent: &Path) -> bool {
+        true
+    }
+
+    /// Walks up from `start`, returning the first config found.
+    /// Fails with the root of the repository the search stopped at, if it stopped at one
+    fn search(&self, start: &Path) -> Result<PathBuf, Option<PathBuf>> {
+        let start: PathBuf = fs::canonicalize(start).unwrap_or_else(|_| start.to_path_buf());
+        let mut dir: &Path = &start;
+
+        loop {
+            for name in &self.names {
+                let path: PathBuf = dir.join(name);
+
+                if path.is_file() {
+                    return Ok(path);
+                }
+            }
+
+            if self.repo_boundary && Self::is_repo_root(dir) {
+                return Err(Some(dir.to_path_buf()));
+            }
+
+            match dir.parent() {
+                Some(parent) if !self.filesystem_boundary || Self::same_filesystem(dir, parent) => dir = parent,
+                _ => return Err(None)
+            }
+        }
+    }
+
+    /// Path of the first config found walking up from the directory `start`
+    pub fn find(&self, start: &Path) -> Option<PathBuf> {
+        self.search(start).ok()
+    }
+
+    /// Config file found walking up from the directory `start`.
+    /// When there's none, the config file is placed in the root of the repository if the search reached one, otherwise in `start`
+    pub fn file<Format: format::Format + Sized + Clone>(&self, start: &Path, format: Format) -> ConfigFile<Format> {
+        let path: PathBuf = match self.search(start) {
+            Ok(path) => path,
+            Err(repo_root) => repo_root.unwrap_or_else(|| start.to_path_buf()).join(self.names.first().map_or("", String::as_str))
+        };
+
+        ConfigFile::new(&path, format)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test::test_path::TestPath;
+    use crate::formats::string_format::StringFormat;
+
+    #[test]
+    fn find_nearest() {
+        let tp: TestPath = TestPath::new();
+
+        fs::create_dir_all(&tp.path).unwrap();
+
+        let root: PathBuf = fs::canonicalize(&tp.path).unwrap();
+        let repo: PathBuf = root.join("repo");
+        let start: PathBuf = repo.join("a").join("b");
+        let d: Discover = Discover::new(&[ ".myapp.toml", "myapp.toml" ]);
+
+        fs::create_dir_all(&start).unwrap();
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::write(root.join(".myapp.toml"), "").unwrap();
+
+        assert_eq!(d.find(&start), None);
+        assert_eq!(d.clone().repo_boundary(false).find(&start), Some(root.join(".myapp.toml")));
+        assert_eq!(&*d.file(&start, StringFormat::new()).path, &*repo.join(".myapp.toml"));
+
+        fs::write(repo.join("a").join("myapp.toml"), "").unwrap();
+        fs::write(repo.join(".myapp.toml"), "").unwrap();
+
+        assert_eq!(d.find(&start), Some(repo.join("a").join("myapp.toml")));
+    }
+
+}
@@ -0,0 +1,263 @@
+use std::error::Error;
+use std::fmt;
+use std::env;
+
+use crate::value::{ Value, Table };
+use crate::format::StructuredFormat;
+
+/// Overrides of config values from environment variables,
+/// like `APP__DATABASE__HOST=db.internal` for `database.host` with the prefix `APP` and the default separator `__`.
+///
+/// Keys are matched to the keys of the config regardless of case, keys the config doesn't have are added in lower case.
+/// Values are converted to the type of the value they override, so `APP__PORT=8080` overrides an integer with an integer,
+/// and new values are read as booleans, integers or floats where they look like one, or as strings if the content doesn't take the guess
+#[derive(Clone, Debug)]
+pub struct EnvOverrides {
+    prefix: String,
+    separator: String
+}
+
+impl EnvOverrides {
+
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: String::from(prefix),
+            separator: String::from("__")
+        }
+    }
+
+    /// Separator between the prefix and the keys of a path, `__` by default
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = String::from(separator);
+        self
+    }
+
+    /// Path of the value a variable overrides, keys matched to the keys of `value` regardless of case.
+    /// `None` if the variable doesn't start with the prefix
+    fn path(&self, value: &Value, var: &str) -> Option<String> {
+        let rest: &str = var.strip_prefix(&self.prefix)?.strip_prefix(&self.separator)?;
+        let mut table: Option<&Table> = value.as_table();
+        let mut path: Vec<String> = Vec::new();
+
+        for key in rest.split(&self.separator) {
+            if key.is_empty() {
+                return None;
+            }
+
+            let __key: String = match table.and_then(|__table| __table.keys().find(|existing| existing.eq_ignore_ascii_case(key))) {
+                Some(existing) => existing.clone(),
+                None => key.to_lowercase()
+            };
+
+            table = table.and_then(|__table| __table.get(&__key)).and_then(Value::as_table);
+            path.push(__key);
+        }
+
+        Some(path.join("."))
+    }
+
+    /// Overrides values of `value` with the environment variables starting with the prefix,
+    /// returning the paths of the values overridden
+    pub fn apply(&self, value: &mut Value) -> Result<Vec<String>, EnvError> {
+        self.apply_guessing(value, &mut Guessed::default())
+    }
+
+    /// Same as `apply`, recording the values whose type was guessed in `guessed`
+    pub(crate) fn apply_guessing(&self, value: &mut Value, guessed: &mut Guessed) -> Result<Vec<String>, EnvError> {
+        let mut vars: Vec<(String, String)> = env::vars_os()
+            .filter_map(|( var, var_value )| Some(( var.into_string().ok()?, var_value.into_string().ok()? )))
+            .collect();
+        let mut overridden: Vec<String> = Vec::new();
+
+        // Sorted so a variable for a table is always overridden by the variables for its keys
+        vars.sort();
+
+        for ( var, var_value ) in vars {
+            let path: String = match self.path(value, &var) {
+                Some(path) => path,
+                None => continue
+            };
+            let __var_value: Value = coerce(value.get(&path), &var_value).map_err(|expected| EnvError {
+                var,
+                value: var_value.clone(),
+                expected
+            })?;
+
+            guessed.record(&path, &var_value, value.get(&path));
+            value.set(&path, __var_value);
+            overridden.push(path);
+        }
+
+        Ok(overridden)
+    }
+
+}
+
+/// Converts the value of a variable to the type of the value it overrides,
+/// failing with the name of the type if it can't be
+pub(crate) fn coerce(existing: Option<&Value>, input: &str) -> Result<Value, &'static str> {
+    match existing {
+        Some(Value::Bool(_)) => parse_bool(input).map(Value::Bool).ok_or("boolean"),
+        Some(Value::Integer(_)) => input.trim().parse().map(Value::Integer).map_err(|_| "integer"),
+        Some(Value::Float(_)) => input.trim().parse().map(Value::Float).map_err(|_| "float"),
+        Some(Value::String(_)) => Ok(Value::from(input)),
+        Some(Value::Array(array)) => if input.is_empty() {
+            Ok(Value::Array(Vec::new()))
+        } else {
+            input.split(',')
+                .map(|item| coerce(array.first(), item.trim()))
+                .collect::<Result<Vec<Value>, &'static str>>()
+                .map(Value::Array)
+        },
+        Some(Value::Table(_)) => Err("table"),
+        Some(Value::Null) | None => Ok(infer(input))
+    }
+}
+
+fn parse_bool(input: &str) -> Option<bool> {
+    match input.trim().to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" => Some(false),
+        _ => None
+    }
+}
+
+/// Reads a value without a type to convert to as a boolean, integer or float if it looks like one, otherwise as a string
+fn infer(input: &str) -> Value {
+    if let Ok(value) = input.parse::<bool>() {
+        return Value::Bool(value);
+    }
+
+    if let Ok(value) = input.parse::<i64>() {
+        return Value::Integer(value);
+    }
+
+    match input.parse::<f64>() {
+        Ok(value) if value.is_finite() => Value::Float(value),
+        _ => Value::from(input)
+    }
+}
+
+/// Overrides whose type was guessed since there was no value to convert them to, by path, as they were given.
+///
+/// A guess like an integer for `1234` doesn't fit a content holding a string there,
+/// so the content is converted again with them as strings when it fails
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Guessed(Vec<( String, String )>);
+
+impl Guessed {
+
+    /// Records `input` given for `path`, which held `existing`, replacing what was recorded for the path
+    pub(crate) fn record(&mut self, path: &str, input: &str, existing: Option<&Value>) {
+        self.forget(path);
+
+        if let None | Some(Value::Null) = existing {
+            self.0.push(( String::from(path), String::from(input) ));
+        }
+    }
+
+    /// Forgets the values at `path`, or within it, overridden by something else
+    pub(crate) fn forget(&mut self, path: &str) {
+        let within = |parent: &str, child: &str| child.strip_prefix(parent).is_some_and(|rest| rest.starts_with('.'));
+
+        self.0.retain(|( guessed, _ )| guessed != path && !within(path, guessed) && !within(guessed, path));
+    }
+
+    /// Converts `value` to the content of `format`, with the guessed values as strings if they don't fit as guessed
+    pub(crate) fn to_content<Format: StructuredFormat>(&self, format: &mut Format, value: Value) -> Result<Format::Content, Format::Error> {
+        if self.0.is_empty() {
+            return format.to_content(value);
+        }
+
+        match format.to_content(value.clone()) {
+            Ok(content) => Ok(content),
+            Err(_) => {
+                let mut value: Value = value;
+
+                for ( path, input ) in &self.0 {
+                    value.set(path, Value::from(input.as_str()));
+                }
+
+                format.to_content(value)
+            }
+        }
+    }
+
+}
+
+/// Error returned when an environment variable can't be converted to the type of the value it overrides
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvError {
+    pub var: String,
+    pub value: String,
+    /// Name of the type of the value overridden
+    pub expected: &'static str
+}
+
+impl fmt::Display for EnvError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "environment variable {} is {:?}, expected {}", self.var, self.value, self.expected)
+    }
+
+}
+
+impl Error for EnvError {}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn value() -> Value {
+        let mut v: Value = Value::default();
+
+        v.set("database.host", Value::from("localhost"));
+        v.set("database.port", Value::from(5432));
+        v.set("debug", Value::from(false));
+        v.set("tags", Value::Array(vec![ Value::from(1) ]));
+
+        v
+    }
+
+    #[test]
+    fn override_coerced() {
+        env::set_var("ENV_COERCED__DATABASE__PORT", "5433");
+        env::set_var("ENV_COERCED__Debug", "yes");
+        env::set_var("ENV_COERCED__TAGS", "2, 3");
+        env::set_var("ENV_COERCED__DATABASE__USER", "admin");
+        env::set_var("ENV_COERCED__TIMEOUT", "1.5");
+
+        let mut v: Value = value();
+        let overridden: Vec<String> = EnvOverrides::new("ENV_COERCED").apply(&mut v).unwrap();
+
+        assert_eq!(v.get("database.host"), Some(&Value::from("localhost")));
+        assert_eq!(v.get("database.port"), Some(&Value::from(5433)));
+        assert_eq!(v.get("database.user"), Some(&Value::from("admin")));
+        assert_eq!(v.get("debug"), Some(&Value::from(true)));
+        assert_eq!(v.get("tags"), Some(&Value::Array(vec![ Value::from(2), Value::from(3) ])));
+        assert_eq!(v.get("timeout"), Some(&Value::from(1.5)));
+        assert_eq!(overridden, vec![ "database.port", "database.user", "debug", "tags", "timeout" ]);
+    }
+
+    #[test]
+    fn separator() {
+        env::set_var("ENV_SEPARATOR_DATABASE_HOST", "db.internal");
+
+        let mut v: Value = value();
+        EnvOverrides::new("ENV_SEPARATOR").separator("_").apply(&mut v).unwrap();
+
+        assert_eq!(v.get("database.host"), Some(&Value::from("db.internal")));
+    }
+
+    #[test]
+    fn type_mismatch() {
+        env::set_var("ENV_MISMATCH__DATABASE__PORT", "default");
+
+        let err: EnvError = EnvOverrides::new("ENV_MISMATCH").apply(&mut value()).err().unwrap();
+
+        assert_eq!(err.var, "ENV_MISMATCH__DATABASE__PORT");
+        assert_eq!(err.expected, "integer");
+    }
+
+}
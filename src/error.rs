@@ -0,0 +1,183 @@
+use std::path::Path;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Step of reading or writing a config that failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Reading the content of a file
+    Read,
+    /// Listing the entries of a directory
+    ReadDir,
+    /// Creating a directory and it's ancestors
+    CreateDir,
+    /// Writing the content of a file
+    Write,
+    /// Deserializing the content of a file with it's format
+    Deserialize,
+    /// Serializing the content of a file with it's format
+    Serialize,
+    /// Backing up a file before it's overwritten
+    Backup,
+    /// Taking the lock of a file
+    Lock,
+    /// Watching a file or directory for changes
+    Watch
+}
+
+impl fmt::Display for Operation {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Operation::Read => "read",
+            Operation::ReadDir => "read directory",
+            Operation::CreateDir => "create directory",
+            Operation::Write => "write",
+            Operation::Deserialize => "deserialize",
+            Operation::Serialize => "serialize",
+            Operation::Backup => "back up",
+            Operation::Lock => "lock",
+            Operation::Watch => "watch"
+        })
+    }
+
+}
+
+/// Error returned when reading or writing a config fails
+#[derive(Debug)]
+pub struct ConfigError {
+    path: Box<Path>,
+    layer: Option<String>,
+    operation: Operation,
+    parents: Vec<Box<Path>>,
+    source: Box<dyn Error + Send + Sync>
+}
+
+impl ConfigError {
+
+    pub(crate) fn new<E: Into<Box<dyn Error + Send + Sync>>>(path: &Path, operation: Operation, err: E) -> Self {
+        Self {
+            path: path.to_path_buf().into_boxed_path(),
+            layer: None,
+            operation,
+            parents: Vec::new(),
+            source: err.into()
+        }
+    }
+
+    pub(crate) fn io(path: &Path, operation: Operation, err: io::Error) -> Self {
+        Self::new(path, operation, err)
+    }
+
+    pub(crate) fn deserialize<E: Error + Send + Sync + 'static>(path: &Path, err: E) -> Self {
+        Self::new(path, Operation::Deserialize, err)
+    }
+
+    pub(crate) fn serialize<E: Error + Send + Sync + 'static>(path: &Path, err: E) -> Self {
+        Self::new(path, Operation::Serialize, err)
+    }
+
+    /// Error of a layer of a `Layered` config that isn't a file, like environment variables
+    pub(crate) fn of_layer<E: Into<Box<dyn Error + Send + Sync>>>(name: &str, operation: Operation, err: E) -> Self {
+        Self::new(Path::new(""), operation, err).in_layer(name)
+    }
+
+    /// Records that the error occurred in the layer `name` of a `Layered` config
+    pub(crate) fn in_layer(mut self, name: &str) -> Self {
+        self.layer = Some(String::from(name));
+        self
+    }
+
+    /// Records that the error occurred while handling a child of the directory at `path`
+    pub(crate) fn within(mut self, path: &Path) -> Self {
+        self.parents.insert(0, path.to_path_buf().into_boxed_path());
+        self
+    }
+
+    /// Path of the file or directory the error occurred at, empty if it didn't occur at one
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Name of the layer of a `Layered` config the error occurred in, if it did
+    pub fn layer(&self) -> Option<&str> {
+        self.layer.as_deref()
+    }
+
+    /// Step that failed
+    pub fn operation(&self) -> Operation {
+        self.operation
+    }
+
+    /// Paths of the `ConfigDirectory`:s that led to the failing config, outermost first
+    pub fn parents(&self) -> &[Box<Path>] {
+        &self.parents
+    }
+
+    /// Underlying I/O error, if the file system failed
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.source.downcast_ref::<io::Error>()
+    }
+
+}
+
+impl fmt::Display for ConfigError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "failed to {}", self.operation)?;
+
+        if !self.path.as_os_str().is_empty() {
+            write!(f, " {}", self.path.display())?;
+        }
+
+        if let Some(layer) = &self.layer {
+            write!(f, " in layer {}", layer)?;
+        }
+
+        write!(f, ": {}", self.source)
+    }
+
+}
+
+impl Error for ConfigError {
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.source)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn parents_outermost_first() {
+        let err: ConfigError = ConfigError::io(Path::new("a/b/c.txt"), Operation::Read, io::Error::from(io::ErrorKind::NotFound))
+            .within(Path::new("a/b"))
+            .within(Path::new("a"));
+
+        assert_eq!(err.parents(), &[Path::new("a").into(), Path::new("a/b").into()]);
+    }
+
+    #[test]
+    fn display_layer() {
+        let err: ConfigError = ConfigError::of_layer("env", Operation::Deserialize, "PORT is not an integer");
+        assert_eq!(err.to_string(), "failed to deserialize in layer env: PORT is not an integer");
+        assert_eq!(err.path(), Path::new(""));
+
+        let err: ConfigError = ConfigError::new(Path::new("a.toml"), Operation::Read, "denied").in_layer("user");
+        assert_eq!(err.to_string(), "failed to read a.toml in layer user: denied");
+    }
+
+    #[test]
+    fn source_io_error() {
+        let err: ConfigError = ConfigError::io(Path::new("a.txt"), Operation::Write, io::Error::from(io::ErrorKind::PermissionDenied));
+
+        assert_eq!(err.io_error().unwrap().kind(), io::ErrorKind::PermissionDenied);
+        assert!(err.source().is_some());
+    }
+
+}
@@ -1,12 +1,74 @@
-pub struct Deserialized<Content>(pub Content, pub bool);
+use std::error::Error;
+use std::path::Path;
+
+use crate::value::Value;
+
+/// What of the deserialized content was filled in from defaults
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Defaulted {
+    /// Nothing, the content was read as is
+    #[default]
+    None,
+    /// All of it, since there was nothing to read
+    All,
+    /// Keys missing from what was read, as paths like `database.port`
+    Keys(Vec<String>)
+}
+
+impl Defaulted {
+
+    /// `Keys` when any keys were filled in, otherwise `None`
+    pub fn from_keys(keys: Vec<String>) -> Self {
+        if keys.is_empty() {
+            Defaulted::None
+        } else {
+            Defaulted::Keys(keys)
+        }
+    }
+
+    pub fn is_defaulted(&self) -> bool {
+        *self != Defaulted::None
+    }
+
+    /// Keys that were filled in, empty unless `Keys`
+    pub fn keys(&self) -> &[String] {
+        match self {
+            Defaulted::Keys(keys) => keys,
+            _ => &[]
+        }
+    }
+
+}
+
+pub struct Deserialized<Content>(pub Content, pub Defaulted);
 
 pub trait Format {
 
     type Content;
     type Defaults: Clone;
+    type Error: Error + Send + Sync + 'static;
+
+    fn deserialize(&mut self, input: Vec<u8>, defaults: Option<&Self::Defaults>) -> Result<Deserialized<Self::Content>, Self::Error>;
+
+    fn serialize(&mut self, input: Option<&Self::Content>) -> Result<Vec<u8>, Self::Error>;
+
+    /// Called with the path of the file before every read and write,
+    /// so formats can adapt to the file they're handling
+    fn select(&mut self, _path: &Path) {}
+
+}
+
+/// Format whose content can be taken apart into a `Value` tree and put back together,
+/// so configs can be layered and overridden key by key
+pub trait StructuredFormat: Format {
+
+    /// Parses a file into a tree without deserializing it as the content type, so it may hold only some of the keys.
+    ///
+    /// Empty input parses as an empty table
+    fn parse(&mut self, input: Vec<u8>) -> Result<Value, Self::Error>;
 
-    fn deserialize(&mut self, input: Vec<u8>, defaults: Option<&Self::Defaults>) -> Deserialized<Self::Content>;
+    fn to_value(&mut self, content: &Self::Content) -> Result<Value, Self::Error>;
 
-    fn serialize(&mut self, input: Option<&Self::Content>) -> Vec<u8>;
+    fn to_content(&mut self, value: Value) -> Result<Self::Content, Self::Error>;
 
-}
\ No newline at end of file
+}
@@ -0,0 +1,376 @@
+use std::path::Path;
+use std::error::Error;
+use std::sync::Arc;
+use std::any::Any;
+use std::fmt;
+
+use crate::format::{ Format, Deserialized };
+
+/// Content of any type, as (de)serialized by `AnyFormat`
+pub struct AnyContent {
+    value: Box<dyn Any + Send>,
+    clone: fn(&(dyn Any + Send)) -> Box<dyn Any + Send>
+}
+
+fn clone_any<T: Any + Clone + Send>(value: &(dyn Any + Send)) -> Box<dyn Any + Send> {
+    Box::new(value.downcast_ref::<T>().unwrap().clone())
+}
+
+impl AnyContent {
+
+    pub fn new<T: Any + Clone + Send>(value: T) -> Self {
+        Self {
+            value: Box::new(value),
+            clone: clone_any::<T>
+        }
+    }
+
+    pub fn is<T: Any>(&self) -> bool {
+        self.value.is::<T>()
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.value.downcast_ref::<T>()
+    }
+
+    pub fn downcast_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.value.downcast_mut::<T>()
+    }
+
+    pub fn downcast<T: Any>(self) -> Result<T, Self> {
+        if self.is::<T>() {
+            Ok(*self.value.downcast::<T>().unwrap())
+        } else {
+            Err(self)
+        }
+    }
+
+}
+
+impl Clone for AnyContent {
+
+    fn clone(&self) -> Self {
+        Self {
+            value: (self.clone)(&*self.value),
+            clone: self.clone
+        }
+    }
+
+}
+
+impl fmt::Debug for AnyContent {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("AnyContent")
+    }
+
+}
+
+/// Object safe counterpart of `Format`, with content of any type.
+///
+/// Implemented for every `Format` that can be shared between threads, whose content can be cloned and sent between threads
+pub trait DynFormat: Send + Sync {
+
+    fn deserialize_dyn(&mut self, input: Vec<u8>, defaults: Option<&AnyContent>) -> Result<Deserialized<AnyContent>, AnyFormatError>;
+
+    fn serialize_dyn(&mut self, input: Option<&AnyContent>) -> Result<Vec<u8>, AnyFormatError>;
+
+    fn select_dyn(&mut self, path: &Path);
+
+    fn clone_dyn(&self) -> Box<dyn DynFormat>;
+
+}
+
+impl<F> DynFormat for F
+where
+    F: Format + Clone + Send + Sync + 'static,
+    F::Content: Clone + Send + 'static,
+    F::Defaults: 'static
+{
+
+    fn deserialize_dyn(&mut self, input: Vec<u8>, defaults: Option<&AnyContent>) -> Result<Deserialized<AnyContent>, AnyFormatError> {
+        let defaults: Option<&F::Defaults> = match defaults {
+            Some(__defaults) => Some(__defaults.downcast_ref::<F::Defaults>().ok_or(AnyFormatError::ContentType)?),
+            None => None
+        };
+        let deserialized: Deserialized<F::Content> = self.deserialize(input, defaults)
+            .map_err(|err| AnyFormatError::Format(Box::new(err)))?;
+
+        Ok(Deserialized(AnyContent::new(deserialized.0), deserialized.1))
+    }
+
+    fn serialize_dyn(&mut self, input: Option<&AnyContent>) -> Result<Vec<u8>, AnyFormatError> {
+        let input: Option<&F::Content> = match input {
+            Some(__input) => Some(__input.downcast_ref::<F::Content>().ok_or(AnyFormatError::ContentType)?),
+            None => None
+        };
+
+        self.serialize(input).map_err(|err| AnyFormatError::Format(Box::new(err)))
+    }
+
+    fn select_dyn(&mut self, path: &Path) {
+        self.select(path);
+    }
+
+    fn clone_dyn(&self) -> Box<dyn DynFormat> {
+        Box::new(self.clone())
+    }
+
+}
+
+impl Clone for Box<dyn DynFormat> {
+
+    fn clone(&self) -> Self {
+        self.clone_dyn()
+    }
+
+}
+
+/// Error returned by `AnyFormat`
+#[derive(Debug)]
+pub enum AnyFormatError {
+    /// No format is registered for the extension or magic bytes of the file
+    NoFormat,
+    /// Content or defaults are not of the content type of the selected format
+    ContentType,
+    /// The selected format failed
+    Format(Box<dyn Error + Send + Sync>)
+}
+
+impl fmt::Display for AnyFormatError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AnyFormatError::NoFormat => f.write_str("no format registered for file"),
+            AnyFormatError::ContentType => f.write_str("content doesn't match the content type of the format"),
+            AnyFormatError::Format(err) => err.fmt(f)
+        }
+    }
+
+}
+
+impl Error for AnyFormatError {
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AnyFormatError::Format(err) => Some(&**err),
+            _ => None
+        }
+    }
+
+}
+
+/// Formats to pick from by file extension or leading magic bytes
+#[derive(Clone, Default)]
+pub struct FormatRegistry {
+    extensions: Vec<(String, Box<dyn DynFormat>)>,
+    magic: Vec<(Vec<u8>, Box<dyn DynFormat>)>,
+    fallback: Option<Box<dyn DynFormat>>
+}
+
+impl FormatRegistry {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses `format` for files with any of `extensions`, given without the leading dot.
+    ///
+    /// Files without an extension, like `.env`, match by their name without the leading dot
+    pub fn extension<F: DynFormat + 'static>(mut self, extensions: &[&str], format: F) -> Self {
+        for extension in extensions {
+            self.extensions.push(( extension.to_lowercase(), format.clone_dyn() ));
+        }
+
+        self
+    }
+
+    /// Uses `format` for files starting with `magic`, when their extension isn't registered
+    pub fn magic<F: DynFormat + 'static>(mut self, magic: &[u8], format: F) -> Self {
+        self.magic.push(( magic.to_vec(), Box::new(format) ));
+        self
+    }
+
+    /// Uses `format` for files matching nothing else
+    pub fn fallback<F: DynFormat + 'static>(mut self, format: F) -> Self {
+        self.fallback = Some(Box::new(format));
+        self
+    }
+
+    pub fn by_path(&self, path: &Path) -> Option<Box<dyn DynFormat>> {
+        let extension: String = match path.extension() {
+            Some(extension) => extension.to_string_lossy().to_lowercase(),
+            None => path.file_name()?.to_string_lossy().trim_start_matches('.').to_lowercase()
+        };
+
+        self.extensions.iter()
+            .find(|( __extension, _ )| *__extension == extension)
+            .map(|( _, format )| format.clone())
+    }
+
+    pub fn by_magic(&self, input: &[u8]) -> Option<Box<dyn DynFormat>> {
+        self.magic.iter()
+            .find(|( magic, _ )| input.starts_with(magic))
+            .map(|( _, format )| format.clone())
+    }
+
+}
+
+/// Format picking the format to use for each file from a `FormatRegistry`,
+/// by the extension of the file, then the magic bytes it starts with, then the fallback of the registry.
+///
+/// Useful with `ConfigDirectory` and `read_new`, for directories holding files of several formats
+#[derive(Clone)]
+pub struct AnyFormat {
+    registry: Arc<FormatRegistry>,
+    format: Option<Box<dyn DynFormat>>,
+    path: Option<Box<Path>>
+}
+
+impl AnyFormat {
+
+    pub fn new(registry: FormatRegistry) -> Self {
+        Self {
+            registry: Arc::new(registry),
+            format: None,
+            path: None
+        }
+    }
+
+}
+
+impl Format for AnyFormat {
+
+    type Content = AnyContent;
+    type Defaults = AnyContent;
+    type Error = AnyFormatError;
+
+    fn deserialize(&mut self, input: Vec<u8>, defaults: Option<&Self::Defaults>) -> Result<Deserialized<Self::Content>, Self::Error> {
+        if self.format.is_none() {
+            self.format = self.registry.by_magic(&input).or_else(|| self.registry.fallback.clone());
+        }
+
+        match &mut self.format {
+            Some(format) => format.deserialize_dyn(input, defaults),
+            None => Err(AnyFormatError::NoFormat)
+        }
+    }
+
+    fn serialize(&mut self, input: Option<&Self::Content>) -> Result<Vec<u8>, Self::Error> {
+        if self.format.is_none() {
+            self.format = self.registry.fallback.clone();
+        }
+
+        match &mut self.format {
+            Some(format) => format.serialize_dyn(input),
+            None => Err(AnyFormatError::NoFormat)
+        }
+    }
+
+    fn select(&mut self, path: &Path) {
+        // Keep the format picked for the same file, it may have been picked by magic bytes
+        if self.path.as_deref() != Some(path) {
+            self.format = self.registry.by_path(path);
+            self.path = Some(path.into());
+        }
+
+        if let Some(format) = &mut self.format {
+            format.select_dyn(path);
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::formats::string_format::StringFormat;
+    use crate::formats::ini_format::{ IniFormat, Ini };
+    use crate::formats::env_file_format::{ EnvFileFormat, EnvFile };
+
+    fn any_format() -> AnyFormat {
+        AnyFormat::new(FormatRegistry::new()
+            .extension(&["ini", "conf"], IniFormat::new())
+            .extension(&["env"], EnvFileFormat::new())
+            .magic(b"[", IniFormat::new())
+            .fallback(StringFormat::new()))
+    }
+
+    fn deserialize(f: &mut AnyFormat, path: &str, input: &str) -> AnyContent {
+        f.select(Path::new(path));
+        f.deserialize(input.as_bytes().to_vec(), None).unwrap().0
+    }
+
+    #[test]
+    fn select_by_extension() {
+        let mut f: AnyFormat = any_format();
+
+        let c: AnyContent = deserialize(&mut f, "a/server.CONF", "port = 8080\n");
+        assert_eq!(c.downcast_ref::<Ini>().unwrap().get("", "port"), Some("8080"));
+
+        let c: AnyContent = deserialize(&mut f, "a/.env", "PORT=8080\n");
+        assert_eq!(c.downcast_ref::<EnvFile>().unwrap().get("PORT"), Some("8080"));
+    }
+
+    #[test]
+    fn select_by_magic() {
+        let mut f: AnyFormat = any_format();
+        let c: AnyContent = deserialize(&mut f, "a/server", "[server]\nport = 8080\n");
+        assert_eq!(c.downcast_ref::<Ini>().unwrap().get("server", "port"), Some("8080"));
+    }
+
+    #[test]
+    fn select_fallback() {
+        let mut f: AnyFormat = any_format();
+        let c: AnyContent = deserialize(&mut f, "a/notes.txt", "Hello, world!");
+        assert_eq!(c.downcast::<String>().unwrap(), "Hello, world!");
+    }
+
+    #[test]
+    fn no_format() {
+        let mut f: AnyFormat = AnyFormat::new(FormatRegistry::new());
+
+        f.select(Path::new("a/notes.txt"));
+
+        match f.deserialize(b"Hello, world!".to_vec(), None) {
+            Err(AnyFormatError::NoFormat) => (),
+            _ => panic!("Expected a no format error")
+        }
+    }
+
+    #[test]
+    fn defaults_content_type() {
+        let mut f: AnyFormat = any_format();
+        let d: AnyContent = AnyContent::new(String::from("Hello, world!"));
+
+        f.select(Path::new("a/server.ini"));
+
+        match f.deserialize(vec![], Some(&d)) {
+            Err(AnyFormatError::ContentType) => (),
+            _ => panic!("Expected a content type error")
+        }
+    }
+
+    #[test]
+    fn serialize_selected() {
+        let mut f: AnyFormat = any_format();
+        let mut e: EnvFile = EnvFile::new();
+
+        e.set("PORT", "8080");
+        f.select(Path::new("a/.env"));
+
+        assert_eq!(f.serialize(Some(&AnyContent::new(e))).unwrap(), b"PORT=8080\n".to_vec());
+    }
+
+    #[test]
+    fn serialize_magic_selected() {
+        let mut f: AnyFormat = any_format();
+        let c: AnyContent = deserialize(&mut f, "a/server", "[server]\nport = 8080\n");
+
+        f.select(Path::new("a/server"));
+
+        assert_eq!(f.serialize(Some(&c)).unwrap(), b"[server]\nport = 8080\n".to_vec());
+    }
+
+}
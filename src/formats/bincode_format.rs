@@ -0,0 +1,125 @@
+use std::marker::PhantomData;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::format::{ Format, Deserialized, Defaulted };
+use crate::formats::codec_error::CodecError;
+use crate::formats::empty;
+
+/// Format for bincode files, (de)serializing content with serde.
+///
+/// Bincode stores no keys, so files can't be read as values by key: it's not a `StructuredFormat` and can't merge defaults.
+/// Unlike `MsgPackFormat` and `CborFormat`, it can't replace `JsonFormat` in code using `env`, `args`, `interpolate`, `Layered` or `Profiles`
+pub struct BincodeFormat<T> {
+    content: PhantomData<fn() -> T>
+}
+
+#[allow(clippy::new_without_default)]
+impl<T> BincodeFormat<T> {
+
+    pub fn new() -> Self {
+        Self {
+            content: PhantomData
+        }
+    }
+
+}
+
+impl<T> Clone for BincodeFormat<T> {
+
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> Format for BincodeFormat<T> {
+
+    type Content = T;
+    type Defaults = T;
+    type Error = CodecError<bincode::error::DecodeError, bincode::error::EncodeError>;
+
+    fn deserialize(&mut self, input: Vec<u8>, defaults: Option<&Self::Defaults>) -> Result<Deserialized<Self::Content>, Self::Error> {
+        if !input.is_empty() {
+            let ( content, read ) = bincode::serde::decode_from_slice(&input, bincode::config::standard()).map_err(CodecError::Decode)?;
+
+            if read < input.len() {
+                return Err(CodecError::Decode(bincode::error::DecodeError::OtherString(format!("{} trailing bytes after the content", input.len() - read))));
+            }
+
+            Ok(Deserialized(content, Defaulted::None))
+        } else {
+            Ok(Deserialized(match defaults {
+                Some(__defaults) => __defaults.clone(),
+                None => empty::empty().map_err(CodecError::Decode)?
+            }, Defaulted::All))
+        }
+    }
+
+    fn serialize(&mut self, input: Option<&Self::Content>) -> Result<Vec<u8>, Self::Error> {
+        match input {
+            Some(__input) => bincode::serde::encode_to_vec(__input, bincode::config::standard()).map_err(CodecError::Encode),
+            None => Ok(Vec::new())
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+    struct Snapshot {
+        flags: Vec<String>,
+        version: u64
+    }
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            flags: vec![String::from("dark_mode"), String::from("beta")],
+            version: 42
+        }
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        let mut f: BincodeFormat<Snapshot> = BincodeFormat::new();
+        let b: Vec<u8> = f.serialize(Some(&snapshot())).unwrap();
+        let d: Deserialized<Snapshot> = f.deserialize(b, None).unwrap();
+        assert_eq!(d.0, snapshot());
+        assert_eq!(d.1, Defaulted::None);
+    }
+
+    #[test]
+    fn deserialize_defaults() {
+        let mut f: BincodeFormat<Snapshot> = BincodeFormat::new();
+        let d: Deserialized<Snapshot> = f.deserialize(vec![], Some(&snapshot())).unwrap();
+        assert_eq!(d.0, snapshot());
+        assert_eq!(d.1, Defaulted::All);
+    }
+
+    #[test]
+    fn deserialize_empty_without_defaults() {
+        let mut f: BincodeFormat<Option<Snapshot>> = BincodeFormat::new();
+        assert_eq!(f.deserialize(vec![], None).unwrap().0, None);
+    }
+
+    #[test]
+    fn deserialize_invalid() {
+        let mut f: BincodeFormat<Snapshot> = BincodeFormat::new();
+        assert!(f.deserialize(vec![0xff], None).is_err());
+    }
+
+    #[test]
+    fn deserialize_trailing_bytes() {
+        let mut f: BincodeFormat<Snapshot> = BincodeFormat::new();
+        let mut b: Vec<u8> = f.serialize(Some(&snapshot())).unwrap();
+        b.push(0);
+        assert!(matches!(f.deserialize(b, None), Err(CodecError::Decode(bincode::error::DecodeError::OtherString(_)))));
+    }
+
+}
@@ -0,0 +1,180 @@
+use std::marker::PhantomData;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::format::{ Format, StructuredFormat, Deserialized, Defaulted };
+use crate::formats::codec_error::CodecError;
+use crate::formats::empty;
+use crate::formats::merge;
+use crate::value::Value;
+
+/// Format for CBOR files, (de)serializing content with serde.
+///
+/// Merging defaults and overriding keys go through JSON values, where byte strings and tags aren't supported
+pub struct CborFormat<T> {
+    merge_defaults: bool,
+    content: PhantomData<fn() -> T>
+}
+
+#[allow(clippy::new_without_default)]
+impl<T> CborFormat<T> {
+
+    pub fn new() -> Self {
+        Self {
+            merge_defaults: false,
+            content: PhantomData
+        }
+    }
+
+    /// Whether to fill in keys missing from the file with the defaults, instead of only using the defaults for empty files
+    pub fn merge_defaults(mut self, merge_defaults: bool) -> Self {
+        self.merge_defaults = merge_defaults;
+        self
+    }
+
+}
+
+impl<T> Clone for CborFormat<T> {
+
+    fn clone(&self) -> Self {
+        Self::new().merge_defaults(self.merge_defaults)
+    }
+
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> Format for CborFormat<T> {
+
+    type Content = T;
+    type Defaults = T;
+    type Error = CodecError<ciborium::de::Error<std::io::Error>, ciborium::ser::Error<std::io::Error>>;
+
+    fn deserialize(&mut self, input: Vec<u8>, defaults: Option<&Self::Defaults>) -> Result<Deserialized<Self::Content>, Self::Error> {
+        if !input.is_empty() {
+            match defaults {
+                Some(__defaults) if self.merge_defaults => {
+                    let mut value: serde_json::Value = ciborium::from_reader(input.as_slice()).map_err(CodecError::Decode)?;
+                    let mut defaulted: Vec<String> = Vec::new();
+
+                    merge::merge_json(&mut value, serde_json::to_value(__defaults).map_err(serde::ser::Error::custom)
+                        .map_err(CodecError::Encode)?, "", &mut defaulted);
+
+                    let content: T = serde_json::from_value(value).map_err(serde::de::Error::custom)
+                        .map_err(CodecError::Decode)?;
+
+                    Ok(Deserialized(content, Defaulted::from_keys(defaulted)))
+                },
+                _ => Ok(Deserialized(ciborium::from_reader(input.as_slice()).map_err(CodecError::Decode)?, Defaulted::None))
+            }
+        } else {
+            Ok(Deserialized(match defaults {
+                Some(__defaults) => __defaults.clone(),
+                None => empty::empty().map_err(CodecError::Decode)?
+            }, Defaulted::All))
+        }
+    }
+
+    fn serialize(&mut self, input: Option<&Self::Content>) -> Result<Vec<u8>, Self::Error> {
+        let mut output: Vec<u8> = Vec::new();
+
+        if let Some(__input) = input {
+            ciborium::into_writer(__input, &mut output).map_err(CodecError::Encode)?;
+        }
+
+        Ok(output)
+    }
+
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> StructuredFormat for CborFormat<T> {
+
+    fn parse(&mut self, input: Vec<u8>) -> Result<Value, Self::Error> {
+        if input.is_empty() {
+            return Ok(Value::default());
+        }
+
+        let value: serde_json::Value = ciborium::from_reader(input.as_slice()).map_err(CodecError::Decode)?;
+        Ok(Value::from(value))
+    }
+
+    fn to_value(&mut self, content: &Self::Content) -> Result<Value, Self::Error> {
+        Ok(Value::from(serde_json::to_value(content).map_err(serde::ser::Error::custom)
+            .map_err(CodecError::Encode)?))
+    }
+
+    fn to_content(&mut self, value: Value) -> Result<Self::Content, Self::Error> {
+        serde_json::from_value(serde_json::Value::from(value)).map_err(serde::de::Error::custom)
+            .map_err(CodecError::Decode)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+    struct Snapshot {
+        flags: Vec<String>,
+        version: u64
+    }
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            flags: vec![String::from("dark_mode"), String::from("beta")],
+            version: 42
+        }
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        let mut f: CborFormat<Snapshot> = CborFormat::new();
+        let b: Vec<u8> = f.serialize(Some(&snapshot())).unwrap();
+        let d: Deserialized<Snapshot> = f.deserialize(b, None).unwrap();
+        assert_eq!(d.0, snapshot());
+        assert_eq!(d.1, Defaulted::None);
+    }
+
+    #[test]
+    fn deserialize_defaults() {
+        let mut f: CborFormat<Snapshot> = CborFormat::new();
+        let d: Deserialized<Snapshot> = f.deserialize(vec![], Some(&snapshot())).unwrap();
+        assert_eq!(d.0, snapshot());
+        assert_eq!(d.1, Defaulted::All);
+    }
+
+    #[test]
+    fn deserialize_merge_defaults() {
+        let mut f: CborFormat<Snapshot> = CborFormat::new().merge_defaults(true);
+        let b: Vec<u8> = CborFormat::new().serialize(Some(&serde_json::json!({ "version": 42 }))).unwrap();
+        let d: Deserialized<Snapshot> = f.deserialize(b, Some(&snapshot())).unwrap();
+        assert_eq!(d.0, snapshot());
+        assert_eq!(d.1, Defaulted::Keys(vec![ String::from("flags") ]));
+    }
+
+    #[test]
+    fn value_round_trip() {
+        let mut f: CborFormat<Snapshot> = CborFormat::new();
+        let b: Vec<u8> = f.serialize(Some(&snapshot())).unwrap();
+        let mut v: Value = f.parse(b).unwrap();
+        assert_eq!(v.get("version"), Some(&Value::Integer(42)));
+
+        v.set("version", Value::Integer(43));
+        assert_eq!(f.to_content(v).unwrap(), Snapshot { version: 43, ..snapshot() });
+    }
+
+    #[test]
+    fn deserialize_empty_without_defaults() {
+        let mut f: CborFormat<Option<Snapshot>> = CborFormat::new();
+        assert_eq!(f.deserialize(vec![], None).unwrap().0, None);
+    }
+
+    #[test]
+    fn deserialize_invalid() {
+        let mut f: CborFormat<Snapshot> = CborFormat::new();
+        assert!(f.deserialize(vec![0xff], None).is_err());
+    }
+
+}
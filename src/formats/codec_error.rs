@@ -0,0 +1,33 @@
+use std::error::Error;
+use std::fmt;
+
+/// Error returned by formats wrapping a separate decoder and encoder
+#[derive(Debug)]
+pub enum CodecError<De, Ser> {
+    /// The file isn't a valid encoding of the content type
+    Decode(De),
+    /// The content can't be encoded
+    Encode(Ser)
+}
+
+impl<De: fmt::Display, Ser: fmt::Display> fmt::Display for CodecError<De, Ser> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CodecError::Decode(err) => err.fmt(f),
+            CodecError::Encode(err) => err.fmt(f)
+        }
+    }
+
+}
+
+impl<De: Error + 'static, Ser: Error + 'static> Error for CodecError<De, Ser> {
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CodecError::Decode(err) => Some(err),
+            CodecError::Encode(err) => Some(err)
+        }
+    }
+
+}
@@ -0,0 +1,74 @@
+//! Content of an empty or missing file read without defaults, the same for every serde format
+
+use std::marker::PhantomData;
+use std::iter;
+
+use serde::de::{ self, Deserializer, DeserializeOwned, Visitor };
+use serde::de::value::{ MapDeserializer, SeqDeserializer };
+
+/// Deserializes the content of an empty file: `None` for options, `()` for units,
+/// an empty sequence for sequences and an empty map for everything else,
+/// so structs whose fields all have defaults are filled in with them
+pub(crate) fn empty<T: DeserializeOwned, E: de::Error>() -> Result<T, E> {
+    T::deserialize(Empty(PhantomData))
+}
+
+struct Empty<E>(PhantomData<E>);
+
+impl<'de, E: de::Error> Deserializer<'de> for Empty<E> {
+
+    type Error = E;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, E> {
+        visitor.visit_map(MapDeserializer::new(iter::empty::<( (), () )>()))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, E> {
+        visitor.visit_none()
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, E> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, E> {
+        visitor.visit_seq(SeqDeserializer::new(iter::empty::<()>()))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit_struct newtype_struct tuple tuple_struct map struct enum identifier ignored_any
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::collections::HashMap;
+    use serde::Deserialize;
+    use serde::de::value::Error;
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Settings {
+        #[serde(default)]
+        name: String,
+        port: Option<u16>
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Required {
+        name: String
+    }
+
+    #[test]
+    fn empty_content() {
+        assert_eq!(empty::<Settings, Error>().unwrap(), Settings { name: String::new(), port: None });
+        assert_eq!(empty::<Option<Required>, Error>().unwrap(), None);
+        assert_eq!(empty::<Vec<u16>, Error>().unwrap(), Vec::<u16>::new());
+        assert_eq!(empty::<HashMap<String, u16>, Error>().unwrap(), HashMap::new());
+        assert!(empty::<Required, Error>().is_err());
+    }
+
+}
@@ -0,0 +1,710 @@
+use std::collections::HashMap;
+use std::str::Utf8Error;
+use std::error::Error;
+use std::iter::Peekable;
+use std::str::Chars;
+use std::env;
+use std::fmt;
+
+use crate::format::{ Format, StructuredFormat, Deserialized, Defaulted };
+use crate::value::{ Value, Table };
+use crate::interpolate;
+
+/// Ordered variables of a .env file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EnvFile {
+    entries: Vec<(String, String)>
+}
+
+impl EnvFile {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.iter()
+            .find(|( __key, _ )| __key == key)
+            .map(|( _, value )| value.as_str())
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Sets `key` to `value`, keeping it's position if it already exists
+    pub fn set(&mut self, key: &str, value: &str) {
+        match self.entries.iter_mut().find(|( __key, _ )| __key == key) {
+            Some(( _, __value )) => *__value = String::from(value),
+            None => self.entries.push(( String::from(key), String::from(value) ))
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let index: usize = self.entries.iter().position(|( __key, _ )| __key == key)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|( key, value )| ( key.as_str(), value.as_str() ))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+}
+
+/// Error returned by `EnvFileFormat`
+#[derive(Debug)]
+pub enum EnvFileError {
+    /// The file is not valid UTF-8
+    Utf8(Utf8Error),
+    /// Line is neither a comment nor a `KEY=value` pair
+    Syntax(usize),
+    /// Quoted value or `${` expansion starting at the line is never closed
+    Unterminated(usize)
+}
+
+impl fmt::Display for EnvFileError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EnvFileError::Utf8(err) => err.fmt(f),
+            EnvFileError::Syntax(line) => write!(f, "line {}: expected KEY=value", line),
+            EnvFileError::Unterminated(line) => write!(f, "line {}: unterminated quote or expansion", line)
+        }
+    }
+
+}
+
+impl Error for EnvFileError {
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EnvFileError::Utf8(err) => Some(err),
+            _ => None
+        }
+    }
+
+}
+
+#[derive(Clone)]
+enum Line {
+    /// Blank lines, comments and whitespace between variables
+    Other(String),
+    Entry {
+        key: String,
+        /// Text of the line up to the value, like `export KEY=`
+        prefix: String,
+        /// Value as written, with quotes and references
+        raw: String,
+        /// Text after the value up to the next line, like ` # comment\n`
+        suffix: String,
+        value: String
+    }
+}
+
+/// Format for .env files.
+///
+/// Lines are `KEY=value` pairs, optionally prefixed with `export`, or comments starting with `#`.
+/// Values may be single quoted (taken literally), double quoted (with `\n`, `\t`, `\"`, `\\` and `\$` escapes,
+/// spanning lines) or unquoted (ending at the line or a ` #` comment).
+/// `${KEY}` in double quoted and unquoted values expands to an earlier variable of the file,
+/// or, if enabled, a variable of the process environment.
+/// References like `${env:VAR}` are left for `ConfigFile::interpolate`.
+///
+/// The last file read or written is kept, so serializing leaves comments, quotes and references of unchanged variables as they were.
+/// New variables are added at the end, quoted only where needed
+#[derive(Clone)]
+pub struct EnvFileFormat {
+    process_env: bool,
+    export: bool,
+    merge_defaults: bool,
+    document: Option<Vec<Line>>
+}
+
+#[allow(clippy::new_without_default)]
+impl EnvFileFormat {
+
+    pub fn new() -> Self {
+        Self {
+            process_env: false,
+            export: false,
+            merge_defaults: false,
+            document: None
+        }
+    }
+
+    /// Whether `${KEY}` falls back to the process environment, disabled by default
+    pub fn process_env(mut self, process_env: bool) -> Self {
+        self.process_env = process_env;
+        self
+    }
+
+    /// Whether to prefix written variables with `export`, disabled by default
+    pub fn export(mut self, export: bool) -> Self {
+        self.export = export;
+        self
+    }
+
+    /// Whether to fill in keys missing from the file with the defaults, instead of only using the defaults for empty files
+    pub fn merge_defaults(mut self, merge_defaults: bool) -> Self {
+        self.merge_defaults = merge_defaults;
+        self
+    }
+
+    fn lookup(&self, env_file: &EnvFile, key: &str) -> String {
+        match env_file.get(key) {
+            Some(value) => String::from(value),
+            None if self.process_env => env::var(key).unwrap_or_default(),
+            None => String::new()
+        }
+    }
+
+    fn parse_str(&self, input: &str) -> Result<( EnvFile, Vec<Line> ), EnvFileError> {
+        let mut parser: Parser = Parser {
+            chars: input.chars().peekable(),
+            line: 1,
+            position: 0
+        };
+        let mut env_file: EnvFile = EnvFile::new();
+        let mut lines: Vec<Line> = Vec::new();
+        // End of the last variable
+        let mut end: usize = 0;
+
+        loop {
+            parser.skip_whitespace(true);
+
+            match parser.chars.peek() {
+                None => break,
+                Some('#') => {
+                    parser.skip_line();
+                    continue;
+                },
+                _ => ()
+            }
+
+            let key_start: usize = parser.position;
+            let mut key: String = parser.key();
+
+            if key == "export" && parser.chars.peek().is_some_and(|c| *c == ' ' || *c == '\t') {
+                parser.skip_whitespace(false);
+                key = parser.key();
+            }
+
+            if key.is_empty() {
+                return Err(EnvFileError::Syntax(parser.line));
+            }
+
+            parser.skip_whitespace(false);
+
+            if parser.next() != Some('=') {
+                return Err(EnvFileError::Syntax(parser.line));
+            }
+
+            parser.skip_whitespace(false);
+
+            let line: usize = parser.line;
+            let value_start: usize = parser.position;
+            let value: String = match parser.chars.peek() {
+                Some('\'') => {
+                    parser.next();
+                    parser.single_quoted().ok_or(EnvFileError::Unterminated(line))?
+                },
+                Some('"') => {
+                    parser.next();
+                    parser.double_quoted(|key| self.lookup(&env_file, key)).ok_or(EnvFileError::Unterminated(line))?
+                },
+                _ => parser.unquoted(|key| self.lookup(&env_file, key)).ok_or(EnvFileError::Unterminated(line))?
+            };
+
+            let value_end: usize = value_start + input[value_start..parser.position].trim_end().len();
+
+            // Only a comment may follow a value
+            parser.skip_whitespace(false);
+
+            match parser.chars.peek() {
+                None | Some('\n') => (),
+                Some('#') => parser.skip_line(),
+                Some(_) => return Err(EnvFileError::Syntax(parser.line))
+            }
+
+            parser.next();
+
+            let start: usize = input[..key_start].rfind('\n').map_or(0, |i| i + 1);
+
+            if start > end {
+                lines.push(Line::Other(String::from(&input[end..start])));
+            }
+
+            lines.push(Line::Entry {
+                key: key.clone(),
+                prefix: String::from(&input[start..value_start]),
+                raw: String::from(&input[value_start..value_end]),
+                suffix: String::from(&input[value_end..parser.position]),
+                value: value.clone()
+            });
+            end = parser.position;
+            env_file.set(&key, &value);
+        }
+
+        if end < input.len() {
+            lines.push(Line::Other(String::from(&input[end..])));
+        }
+
+        Ok(( env_file, lines ))
+    }
+
+    fn write_entry(&self, output: &mut String, key: &str, value: &str) {
+        if self.export {
+            output.push_str("export ");
+        }
+
+        output.push_str(key);
+        output.push('=');
+        output.push_str(&quote(value));
+        output.push('\n');
+    }
+
+    /// Writes `content` in the layout of `lines`, only touching the variables that changed.
+    ///
+    /// A variable set more than once only has the value of the last line, which is the one read
+    fn render(&self, lines: &[Line], content: &EnvFile) -> String {
+        let mut output: String = String::new();
+        let mut totals: HashMap<&str, usize> = HashMap::new();
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+
+        for line in lines {
+            if let Line::Entry { key, .. } = line {
+                *totals.entry(key).or_insert(0) += 1;
+            }
+        }
+
+        for line in lines {
+            match line {
+                Line::Other(text) => output.push_str(text),
+                Line::Entry { key, prefix, raw, suffix, value } => {
+                    let count: &mut usize = counts.entry(key).or_insert(0);
+
+                    *count += 1;
+
+                    match content.get(key) {
+                        Some(new_value) if *count == totals[key.as_str()] && new_value != value => {
+                            output.push_str(prefix);
+                            output.push_str(&quote(new_value));
+                            output.push_str(suffix);
+                        },
+                        Some(_) => {
+                            output.push_str(prefix);
+                            output.push_str(raw);
+                            output.push_str(suffix);
+                        },
+                        None => ()
+                    }
+                }
+            }
+        }
+
+        for ( key, value ) in content.iter().filter(|( key, _ )| !totals.contains_key(key)) {
+            if !output.is_empty() && !output.ends_with('\n') {
+                output.push('\n');
+            }
+
+            self.write_entry(&mut output, key, value);
+        }
+
+        output
+    }
+
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    /// Byte offset of the next character
+    position: usize
+}
+
+impl<'a> Parser<'a> {
+
+    fn next(&mut self) -> Option<char> {
+        let c: Option<char> = self.chars.next();
+
+        if c == Some('\n') {
+            self.line += 1;
+        }
+
+        if let Some(__c) = c {
+            self.position += __c.len_utf8();
+        }
+
+        c
+    }
+
+    fn skip_whitespace(&mut self, newlines: bool) {
+        while let Some(c) = self.chars.peek() {
+            if *c == ' ' || *c == '\t' || *c == '\r' || (newlines && *c == '\n') {
+                self.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.chars.peek() {
+            if *c == '\n' {
+                break;
+            }
+
+            self.next();
+        }
+    }
+
+    fn key(&mut self) -> String {
+        let mut key: String = String::new();
+
+        while let Some(c) = self.chars.peek() {
+            if c.is_ascii_alphanumeric() || *c == '_' || *c == '.' || *c == '-' {
+                key.push(*c);
+                self.next();
+            } else {
+                break;
+            }
+        }
+
+        key
+    }
+
+    fn single_quoted(&mut self) -> Option<String> {
+        let mut value: String = String::new();
+
+        loop {
+            match self.next()? {
+                '\'' => return Some(value),
+                c => value.push(c)
+            }
+        }
+    }
+
+    fn double_quoted<F: Fn(&str) -> String>(&mut self, lookup: F) -> Option<String> {
+        let mut value: String = String::new();
+
+        loop {
+            match self.next()? {
+                '"' => return Some(value),
+                '\\' => match self.next()? {
+                    'n' => value.push('\n'),
+                    'r' => value.push('\r'),
+                    't' => value.push('\t'),
+                    c @ '"' | c @ '\\' | c @ '$' => value.push(c),
+                    c => {
+                        value.push('\\');
+                        value.push(c);
+                    }
+                },
+                '$' if self.chars.peek() == Some(&'{') => value.push_str(&self.expansion(&lookup)?),
+                c => value.push(c)
+            }
+        }
+    }
+
+    fn unquoted<F: Fn(&str) -> String>(&mut self, lookup: F) -> Option<String> {
+        let mut value: String = String::new();
+
+        while let Some(c) = self.chars.peek() {
+            match *c {
+                '\n' => break,
+                '#' if value.ends_with(' ') || value.ends_with('\t') => break,
+                '$' => {
+                    self.next();
+
+                    if self.chars.peek() == Some(&'{') {
+                        value.push_str(&self.expansion(&lookup)?);
+                    } else {
+                        value.push('$');
+                    }
+                },
+                c => {
+                    value.push(c);
+                    self.next();
+                }
+            }
+        }
+
+        Some(String::from(value.trim_end()))
+    }
+
+    /// Reads `{KEY}` after a `$`. References to interpolate are kept as they are
+    fn expansion<F: Fn(&str) -> String>(&mut self, lookup: F) -> Option<String> {
+        let mut key: String = String::new();
+
+        self.next();
+
+        loop {
+            match self.next()? {
+                '}' if key.split_once(':').is_some_and(|( source, _ )| interpolate::SOURCES.contains(&source)) => {
+                    return Some(format!("${{{}}}", key));
+                },
+                '}' => return Some(lookup(&key)),
+                '\n' => return None,
+                c => key.push(c)
+            }
+        }
+    }
+
+}
+
+fn quote(value: &str) -> String {
+    let bare: bool = value.chars().all(|c| c.is_ascii_alphanumeric() || "_-./:@,+%=".contains(c));
+
+    if bare {
+        return String::from(value);
+    }
+
+    let mut quoted: String = String::from("\"");
+
+    for c in value.chars() {
+        match c {
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '"' | '\\' | '$' => {
+                quoted.push('\\');
+                quoted.push(c);
+            },
+            c => quoted.push(c)
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+/// Sets the variables of `defaults` missing from `env_file`, returning their names
+fn fill(env_file: &mut EnvFile, defaults: &EnvFile) -> Vec<String> {
+    let mut defaulted: Vec<String> = Vec::new();
+
+    for ( key, value ) in defaults.iter() {
+        if !env_file.contains_key(key) {
+            env_file.set(key, value);
+            defaulted.push(String::from(key));
+        }
+    }
+
+    defaulted
+}
+
+impl Format for EnvFileFormat {
+
+    type Content = EnvFile;
+    type Defaults = EnvFile;
+    type Error = EnvFileError;
+
+    fn deserialize(&mut self, input: Vec<u8>, defaults: Option<&Self::Defaults>) -> Result<Deserialized<Self::Content>, Self::Error> {
+        if !input.is_empty() {
+            let input: &str = std::str::from_utf8(&input).map_err(EnvFileError::Utf8)?;
+            let ( mut env_file, lines ) = self.parse_str(input)?;
+
+            self.document = Some(lines);
+
+            match defaults {
+                Some(__defaults) if self.merge_defaults => {
+                    let defaulted: Vec<String> = fill(&mut env_file, __defaults);
+                    Ok(Deserialized(env_file, Defaulted::from_keys(defaulted)))
+                },
+                _ => Ok(Deserialized(env_file, Defaulted::None))
+            }
+        } else {
+            self.document = None;
+
+            Ok(Deserialized(match defaults {
+                Some(__defaults) => __defaults.clone(),
+                None => EnvFile::new()
+            }, Defaulted::All))
+        }
+    }
+
+    fn serialize(&mut self, input: Option<&Self::Content>) -> Result<Vec<u8>, Self::Error> {
+        let input: &EnvFile = match input {
+            Some(__input) => __input,
+            None => return Ok(Vec::new())
+        };
+        let output: String = self.render(self.document.as_deref().unwrap_or(&[]), input);
+
+        // Keep the written layout for the next write
+        self.document = Some(self.parse_str(&output)?.1);
+
+        Ok(output.into_bytes())
+    }
+
+}
+
+/// Variables become strings at the top level, nested tables are flattened into names like `database.port`
+impl StructuredFormat for EnvFileFormat {
+
+    fn parse(&mut self, input: Vec<u8>) -> Result<Value, Self::Error> {
+        let input: &str = std::str::from_utf8(&input).map_err(EnvFileError::Utf8)?;
+        let ( env_file, _ ) = self.parse_str(input)?;
+
+        self.to_value(&env_file)
+    }
+
+    fn to_value(&mut self, content: &Self::Content) -> Result<Value, Self::Error> {
+        Ok(Value::Table(content.iter()
+            .map(|( key, value )| ( String::from(key), Value::from(value) ))
+            .collect::<Table>()))
+    }
+
+    fn to_content(&mut self, value: Value) -> Result<Self::Content, Self::Error> {
+        let mut env_file: EnvFile = EnvFile::new();
+
+        for ( key, __value ) in value.flatten() {
+            env_file.set(&key, &__value);
+        }
+
+        Ok(env_file)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn deserialize(f: &mut EnvFileFormat, s: &str) -> EnvFile {
+        f.deserialize(s.as_bytes().to_vec(), None).unwrap().0
+    }
+
+    #[test]
+    fn deserialize_values() {
+        let mut f: EnvFileFormat = EnvFileFormat::new();
+        let e: EnvFile = deserialize(&mut f, "# Database\nHOST=localhost\nexport PORT = 5432 # postgres\nEMPTY=\n");
+
+        assert_eq!(e.get("HOST"), Some("localhost"));
+        assert_eq!(e.get("PORT"), Some("5432"));
+        assert_eq!(e.get("EMPTY"), Some(""));
+        assert_eq!(e.iter().map(|( key, _ )| key).collect::<Vec<&str>>(), vec!["HOST", "PORT", "EMPTY"]);
+    }
+
+    #[test]
+    fn deserialize_quotes() {
+        let mut f: EnvFileFormat = EnvFileFormat::new();
+        let e: EnvFile = deserialize(&mut f, "A='${B} \\n'\nB=\"line\\none \\\"quoted\\\"\"\nC=\"multi\nline\"\nD=a#b\n");
+
+        assert_eq!(e.get("A"), Some("${B} \\n"));
+        assert_eq!(e.get("B"), Some("line\none \"quoted\""));
+        assert_eq!(e.get("C"), Some("multi\nline"));
+        assert_eq!(e.get("D"), Some("a#b"));
+    }
+
+    #[test]
+    fn deserialize_expansion() {
+        let mut f: EnvFileFormat = EnvFileFormat::new();
+        let e: EnvFile = deserialize(&mut f, "HOST=localhost\nURL=http://${HOST}:${PORT}/\nLITERAL=\"\\${HOST}\"\n");
+
+        assert_eq!(e.get("URL"), Some("http://localhost:/"));
+        assert_eq!(e.get("LITERAL"), Some("${HOST}"));
+    }
+
+    #[test]
+    fn deserialize_keeps_references() {
+        let mut f: EnvFileFormat = EnvFileFormat::new().process_env(true);
+        let e: EnvFile = deserialize(&mut f, "DIR=/srv\nDATA=${self:DIR}/data\nTOKEN=\"${file:/run/secrets/token}\"\nHOME=${env:HOME}\n");
+
+        assert_eq!(e.get("DATA"), Some("${self:DIR}/data"));
+        assert_eq!(e.get("TOKEN"), Some("${file:/run/secrets/token}"));
+        assert_eq!(e.get("HOME"), Some("${env:HOME}"));
+    }
+
+    #[test]
+    fn deserialize_process_env_expansion() {
+        env::set_var("CRATE_THAT_LOADS_CONFIGS_TEST_HOME", "/home/test");
+
+        let mut f: EnvFileFormat = EnvFileFormat::new().process_env(true);
+        let e: EnvFile = deserialize(&mut f, "DATA=\"${CRATE_THAT_LOADS_CONFIGS_TEST_HOME}/data\"\n");
+
+        assert_eq!(e.get("DATA"), Some("/home/test/data"));
+    }
+
+    #[test]
+    fn deserialize_merge_defaults() {
+        let mut f: EnvFileFormat = EnvFileFormat::new().merge_defaults(true);
+        let mut e: EnvFile = EnvFile::new();
+
+        e.set("HOST", "localhost");
+        e.set("PORT", "80");
+
+        let d: Deserialized<EnvFile> = f.deserialize(b"PORT=8080\n".to_vec(), Some(&e)).unwrap();
+        assert_eq!(d.0.get("HOST"), Some("localhost"));
+        assert_eq!(d.0.get("PORT"), Some("8080"));
+        assert_eq!(d.1, Defaulted::Keys(vec![ String::from("HOST") ]));
+    }
+
+    #[test]
+    fn deserialize_errors() {
+        let mut f: EnvFileFormat = EnvFileFormat::new();
+
+        match f.deserialize(b"A=1\nB \"2\"\n".to_vec(), None) {
+            Err(EnvFileError::Syntax(line)) => assert_eq!(line, 2),
+            _ => panic!("Expected a syntax error")
+        }
+
+        match f.deserialize(b"A=1\nB=\"2\n".to_vec(), None) {
+            Err(EnvFileError::Unterminated(line)) => assert_eq!(line, 2),
+            _ => panic!("Expected an unterminated error")
+        }
+    }
+
+    #[test]
+    fn serialize_stable_order() {
+        let mut f: EnvFileFormat = EnvFileFormat::new();
+        let mut e: EnvFile = deserialize(&mut f, "B=2\nA=1\n");
+
+        e.set("C", "with space");
+        e.set("B", "$HOME \"x\"");
+
+        let b: Vec<u8> = f.serialize(Some(&e)).unwrap();
+        assert_eq!(b, b"B=\"\\$HOME \\\"x\\\"\"\nA=1\nC=\"with space\"\n".to_vec());
+        assert_eq!(f.deserialize(b, None).unwrap().0, e);
+    }
+
+    #[test]
+    fn serialize_keeps_raw_values() {
+        env::set_var("CRATE_THAT_LOADS_CONFIGS_TEST_SECRET", "hunter2");
+
+        let s: &str = "# Database\nexport HOST = 'localhost' # default\n\nPASSWORD=${CRATE_THAT_LOADS_CONFIGS_TEST_SECRET}\nPORT=5432\nPORT=5433";
+        let mut f: EnvFileFormat = EnvFileFormat::new().process_env(true);
+        let mut e: EnvFile = deserialize(&mut f, s);
+
+        assert_eq!(e.get("PASSWORD"), Some("hunter2"));
+        assert_eq!(f.serialize(Some(&e)).unwrap(), s.as_bytes().to_vec());
+
+        e.set("HOST", "db.internal");
+        e.set("PORT", "5434");
+        e.set("USER", "admin");
+
+        let b: Vec<u8> = f.serialize(Some(&e)).unwrap();
+        assert_eq!(
+            String::from_utf8(b).unwrap(),
+            "# Database\nexport HOST = db.internal # default\n\nPASSWORD=${CRATE_THAT_LOADS_CONFIGS_TEST_SECRET}\nPORT=5432\nPORT=5434\nUSER=admin\n"
+        );
+    }
+
+    #[test]
+    fn serialize_export() {
+        let mut f: EnvFileFormat = EnvFileFormat::new().export(true);
+        let mut e: EnvFile = EnvFile::new();
+
+        e.set("A", "1");
+
+        assert_eq!(f.serialize(Some(&e)).unwrap(), b"export A=1\n".to_vec());
+    }
+
+}
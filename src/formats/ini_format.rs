@@ -0,0 +1,797 @@
+use std::collections::HashMap;
+use std::str::Utf8Error;
+use std::error::Error;
+use std::fmt;
+
+use crate::format::{ Format, StructuredFormat, Deserialized, Defaulted };
+use crate::formats::merge;
+use crate::value::{ Value, Table };
+
+/// Ordered keys of an INI section, each holding one or more values
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IniSection {
+    entries: Vec<(String, Vec<String>)>
+}
+
+impl IniSection {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// First value of `key`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.get_all(key).and_then(|values| values.first()).map(|value| value.as_str())
+    }
+
+    /// Every value of `key`, in the order they appear
+    pub fn get_all(&self, key: &str) -> Option<&[String]> {
+        self.entries.iter()
+            .find(|( __key, _ )| __key == key)
+            .map(|( _, values )| values.as_slice())
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get_all(key).is_some()
+    }
+
+    /// Sets `key` to `value`, replacing any previous values
+    pub fn set(&mut self, key: &str, value: &str) {
+        match self.entries.iter_mut().find(|( __key, _ )| __key == key) {
+            Some(( _, values )) => *values = vec![String::from(value)],
+            None => self.entries.push(( String::from(key), vec![String::from(value)] ))
+        }
+    }
+
+    /// Adds `value` after any previous values of `key`
+    pub fn add(&mut self, key: &str, value: &str) {
+        match self.entries.iter_mut().find(|( __key, _ )| __key == key) {
+            Some(( _, values )) => values.push(String::from(value)),
+            None => self.entries.push(( String::from(key), vec![String::from(value)] ))
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Vec<String>> {
+        let index: usize = self.entries.iter().position(|( __key, _ )| __key == key)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.entries.iter().map(|( key, values )| ( key.as_str(), values.as_slice() ))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+}
+
+/// Ordered sections of an INI file.
+///
+/// Keys before the first section header belong to the section named `""`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Ini {
+    sections: Vec<(String, IniSection)>
+}
+
+impl Ini {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn section(&self, name: &str) -> Option<&IniSection> {
+        self.sections.iter()
+            .find(|( __name, _ )| __name == name)
+            .map(|( _, section )| section)
+    }
+
+    pub fn section_mut(&mut self, name: &str) -> Option<&mut IniSection> {
+        self.sections.iter_mut()
+            .find(|( __name, _ )| __name == name)
+            .map(|( _, section )| section)
+    }
+
+    /// Section named `name`, inserting an empty one if it doesn't exist
+    pub fn entry(&mut self, name: &str) -> &mut IniSection {
+        let index: usize = match self.sections.iter().position(|( __name, _ )| __name == name) {
+            Some(index) => index,
+            None => {
+                self.sections.push(( String::from(name), IniSection::new() ));
+                self.sections.len() - 1
+            }
+        };
+
+        &mut self.sections[index].1
+    }
+
+    pub fn remove_section(&mut self, name: &str) -> Option<IniSection> {
+        let index: usize = self.sections.iter().position(|( __name, _ )| __name == name)?;
+        Some(self.sections.remove(index).1)
+    }
+
+    pub fn sections(&self) -> impl Iterator<Item = (&str, &IniSection)> {
+        self.sections.iter().map(|( name, section )| ( name.as_str(), section ))
+    }
+
+    /// First value of `key` in section `section`
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.section(section).and_then(|__section| __section.get(key))
+    }
+
+}
+
+/// What to do when a key appears more than once in a section
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Fail to deserialize
+    Error,
+    /// Keep the first value
+    FirstWins,
+    /// Keep the last value
+    LastWins,
+    /// Keep every value, in order
+    Collect
+}
+
+/// Error returned by `IniFormat`
+#[derive(Debug)]
+pub enum IniError {
+    /// The file is not valid UTF-8
+    Utf8(Utf8Error),
+    /// Line is neither a comment, a section header nor a key/value pair
+    Syntax(usize),
+    /// Key appears more than once in a section while duplicates are an error
+    DuplicateKey {
+        line: usize,
+        section: String,
+        key: String
+    }
+}
+
+impl fmt::Display for IniError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IniError::Utf8(err) => err.fmt(f),
+            IniError::Syntax(line) => write!(f, "line {}: expected a section header or a key/value pair", line),
+            IniError::DuplicateKey { line, section, key } => write!(f, "line {}: duplicate key {} in section [{}]", line, key, section)
+        }
+    }
+
+}
+
+impl Error for IniError {
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            IniError::Utf8(err) => Some(err),
+            _ => None
+        }
+    }
+
+}
+
+#[derive(Clone)]
+enum Line {
+    /// Blank line or comment
+    Other(String),
+    Entry {
+        key: String,
+        prefix: String,
+        value: String,
+        suffix: String
+    }
+}
+
+/// Section header and the lines following it, as written in the file
+#[derive(Clone)]
+struct Block {
+    name: String,
+    header: Option<String>,
+    lines: Vec<Line>
+}
+
+#[derive(Clone)]
+struct Document {
+    blocks: Vec<Block>,
+    trailing_newline: bool
+}
+
+/// Format for INI and .conf files.
+///
+/// Lines are `key = value` or `key: value` pairs, `[section]` headers,
+/// or comments starting with `;` or `#`. Values are taken as written, without unquoting.
+///
+/// The last document read or written is kept, so serializing leaves comments and layout as they were
+#[derive(Clone)]
+pub struct IniFormat {
+    duplicates: DuplicateKeys,
+    merge_defaults: bool,
+    document: Option<Document>
+}
+
+#[allow(clippy::new_without_default)]
+impl IniFormat {
+
+    pub fn new() -> Self {
+        Self {
+            duplicates: DuplicateKeys::LastWins,
+            merge_defaults: false,
+            document: None
+        }
+    }
+
+    /// What to do when a key appears more than once in a section, `LastWins` by default
+    pub fn duplicates(mut self, duplicates: DuplicateKeys) -> Self {
+        self.duplicates = duplicates;
+        self
+    }
+
+    /// Whether to fill in keys missing from the sections of the file with the defaults, instead of only using the defaults for empty files
+    pub fn merge_defaults(mut self, merge_defaults: bool) -> Self {
+        self.merge_defaults = merge_defaults;
+        self
+    }
+
+    fn insert(&self, ini: &mut Ini, section: &str, key: &str, value: &str, line: usize) -> Result<(), IniError> {
+        let __section: &mut IniSection = ini.entry(section);
+
+        match self.duplicates {
+            DuplicateKeys::Error if __section.contains_key(key) => Err(IniError::DuplicateKey {
+                line,
+                section: String::from(section),
+                key: String::from(key)
+            }),
+            DuplicateKeys::FirstWins if __section.contains_key(key) => Ok(()),
+            DuplicateKeys::Collect => {
+                __section.add(key, value);
+                Ok(())
+            },
+            _ => {
+                __section.set(key, value);
+                Ok(())
+            }
+        }
+    }
+
+    fn parse_str(&self, input: &str) -> Result<(Ini, Document), IniError> {
+        let mut ini: Ini = Ini::new();
+        let mut blocks: Vec<Block> = vec![Block {
+            name: String::new(),
+            header: None,
+            lines: Vec::new()
+        }];
+        let trailing_newline: bool = input.ends_with('\n');
+        let input: &str = if trailing_newline { &input[..input.len() - 1] } else { input };
+
+        for ( i, raw ) in input.split('\n').enumerate() {
+            let line: &str = raw.trim();
+            let block: &mut Block = blocks.last_mut().unwrap();
+
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                block.lines.push(Line::Other(String::from(raw)));
+            } else if line.starts_with('[') && line.ends_with(']') {
+                let name: &str = line[1..line.len() - 1].trim();
+
+                ini.entry(name);
+                blocks.push(Block {
+                    name: String::from(name),
+                    header: Some(String::from(raw)),
+                    lines: Vec::new()
+                });
+            } else if let Some(separator) = raw.find(['=', ':']) {
+                let key: &str = raw[..separator].trim();
+
+                if key.is_empty() {
+                    return Err(IniError::Syntax(i + 1));
+                }
+
+                let after: &str = &raw[separator + 1..];
+                let value_start: usize = separator + 1 + after.len() - after.trim_start().len();
+                let rest: &str = &raw[value_start..];
+                let value: &str = rest.trim_end();
+
+                self.insert(&mut ini, &block.name, key, value, i + 1)?;
+                block.lines.push(Line::Entry {
+                    key: String::from(key),
+                    prefix: String::from(&raw[..value_start]),
+                    value: String::from(value),
+                    suffix: String::from(&rest[value.len()..])
+                });
+            } else {
+                return Err(IniError::Syntax(i + 1));
+            }
+        }
+
+        Ok(( ini, Document {
+            blocks,
+            trailing_newline
+        } ))
+    }
+
+}
+
+/// Adds the keys of `defaults` missing from `ini`, returning their paths as `section.key`
+fn fill(ini: &mut Ini, defaults: &Ini) -> Vec<String> {
+    let mut defaulted: Vec<String> = Vec::new();
+
+    for ( name, section ) in defaults.sections() {
+        for ( key, values ) in section.iter() {
+            if !ini.section(name).is_some_and(|__section| __section.contains_key(key)) {
+                let __section: &mut IniSection = ini.entry(name);
+
+                for value in values {
+                    __section.add(key, value);
+                }
+
+                defaulted.push(merge::key_path(name, key));
+            }
+        }
+    }
+
+    defaulted
+}
+
+/// Writes `content` in the layout of `document`, only touching the lines of values that changed.
+///
+/// Unless duplicates are collected, a key written more than once only has the value of the line it was read from,
+/// the first or the last, and the other lines are left as they are
+fn render(document: &Document, content: &Ini, duplicates: DuplicateKeys) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut counts: HashMap<( &str, &str ), usize> = HashMap::new();
+    let mut totals: HashMap<( &str, &str ), usize> = HashMap::new();
+    let mut last_blocks: HashMap<&str, usize> = HashMap::new();
+
+    for ( i, block ) in document.blocks.iter().enumerate() {
+        last_blocks.insert(&block.name, i);
+
+        for line in &block.lines {
+            if let Line::Entry { key, .. } = line {
+                *totals.entry(( &block.name, key )).or_insert(0) += 1;
+            }
+        }
+    }
+
+    for ( i, block ) in document.blocks.iter().enumerate() {
+        let section: Option<&IniSection> = content.section(&block.name);
+
+        if block.header.is_some() && section.is_none() {
+            continue;
+        }
+
+        if let Some(header) = &block.header {
+            lines.push(header.clone());
+        }
+
+        let mut insert_at: usize = lines.len();
+
+        for line in &block.lines {
+            match line {
+                Line::Other(raw) => lines.push(raw.clone()),
+                Line::Entry { key, prefix, value, suffix } => {
+                    let count: &mut usize = counts.entry(( &block.name, key )).or_insert(0);
+                    let values: Option<&[String]> = section.and_then(|__section| __section.get_all(key));
+                    let read_from: usize = match duplicates {
+                        DuplicateKeys::LastWins => totals[&( block.name.as_str(), key.as_str() )] - 1,
+                        _ => 0
+                    };
+                    let new_value: Option<&String> = match values {
+                        Some(__values) if duplicates == DuplicateKeys::Collect => __values.get(*count),
+                        Some(__values) if *count == read_from => __values.first(),
+                        Some(_) => Some(value),
+                        None => None
+                    };
+
+                    *count += 1;
+
+                    if let Some(new_value) = new_value {
+                        lines.push(format!("{}{}{}", prefix, new_value, suffix));
+                        insert_at = lines.len();
+                    }
+                }
+            }
+        }
+
+        // New values of the section go after it's last key
+        if last_blocks[block.name.as_str()] == i {
+            if let Some(section) = section {
+                let mut new_lines: Vec<String> = Vec::new();
+
+                for ( key, values ) in section.iter() {
+                    let count: usize = counts.get(&( block.name.as_str(), key )).cloned().unwrap_or(0);
+
+                    for value in values.iter().skip(count) {
+                        new_lines.push(format!("{} = {}", key, value));
+                    }
+                }
+
+                lines.splice(insert_at..insert_at, new_lines);
+            }
+        }
+    }
+
+    for ( name, section ) in content.sections() {
+        if !last_blocks.contains_key(name) {
+            if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                lines.push(String::new());
+            }
+
+            render_section(&mut lines, name, section);
+        }
+    }
+
+    let mut output: String = lines.join("\n");
+
+    if document.trailing_newline {
+        output.push('\n');
+    }
+
+    output
+}
+
+fn render_section(lines: &mut Vec<String>, name: &str, section: &IniSection) {
+    if !name.is_empty() {
+        lines.push(format!("[{}]", name));
+    }
+
+    for ( key, values ) in section.iter() {
+        for value in values {
+            lines.push(format!("{} = {}", key, value));
+        }
+    }
+}
+
+impl Format for IniFormat {
+
+    type Content = Ini;
+    type Defaults = Ini;
+    type Error = IniError;
+
+    fn deserialize(&mut self, input: Vec<u8>, defaults: Option<&Self::Defaults>) -> Result<Deserialized<Self::Content>, Self::Error> {
+        if !input.is_empty() {
+            let input: &str = std::str::from_utf8(&input).map_err(IniError::Utf8)?;
+            let ( ini, document ) = self.parse_str(input)?;
+
+            self.document = Some(document);
+
+            match defaults {
+                Some(__defaults) if self.merge_defaults => {
+                    let mut ini: Ini = ini;
+                    let defaulted: Vec<String> = fill(&mut ini, __defaults);
+
+                    Ok(Deserialized(ini, Defaulted::from_keys(defaulted)))
+                },
+                _ => Ok(Deserialized(ini, Defaulted::None))
+            }
+        } else {
+            self.document = None;
+
+            Ok(Deserialized(match defaults {
+                Some(__defaults) => __defaults.clone(),
+                None => Ini::new()
+            }, Defaulted::All))
+        }
+    }
+
+    fn serialize(&mut self, input: Option<&Self::Content>) -> Result<Vec<u8>, Self::Error> {
+        let input: &Ini = match input {
+            Some(__input) => __input,
+            None => return Ok(Vec::new())
+        };
+        let output: String = match &self.document {
+            Some(document) => render(document, input, self.duplicates),
+            None => {
+                let mut lines: Vec<String> = Vec::new();
+
+                if let Some(section) = input.section("") {
+                    render_section(&mut lines, "", section);
+                }
+
+                for ( name, section ) in input.sections().filter(|( name, _ )| !name.is_empty()) {
+                    if !lines.is_empty() {
+                        lines.push(String::new());
+                    }
+
+                    render_section(&mut lines, name, section);
+                }
+
+                lines.join("\n") + "\n"
+            }
+        };
+
+        // Keep the written layout for the next write.
+        // Duplicates are collected since they were written on purpose
+        self.document = Some(IniFormat::new().duplicates(DuplicateKeys::Collect).parse_str(&output)?.1);
+
+        Ok(output.into_bytes())
+    }
+
+}
+
+/// Sections become tables, keys of the global section are at the top level
+/// and keys with more than one value become arrays of strings
+impl StructuredFormat for IniFormat {
+
+    fn parse(&mut self, input: Vec<u8>) -> Result<Value, Self::Error> {
+        let input: &str = std::str::from_utf8(&input).map_err(IniError::Utf8)?;
+        let ( ini, _ ) = self.parse_str(input)?;
+
+        self.to_value(&ini)
+    }
+
+    fn to_value(&mut self, content: &Self::Content) -> Result<Value, Self::Error> {
+        let mut value: Table = Table::new();
+
+        for ( name, section ) in content.sections() {
+            let mut table: Table = Table::new();
+
+            for ( key, values ) in section.iter() {
+                table.insert(String::from(key), match values {
+                    [ __value ] => Value::from(__value.as_str()),
+                    values => Value::Array(values.iter().map(|__value| Value::from(__value.as_str())).collect())
+                });
+            }
+
+            if name.is_empty() {
+                value.extend(table);
+            } else {
+                value.insert(String::from(name), Value::Table(table));
+            }
+        }
+
+        Ok(Value::Table(value))
+    }
+
+    /// Tables nested deeper than sections are flattened into keys like `database.port`
+    fn to_content(&mut self, value: Value) -> Result<Self::Content, Self::Error> {
+        let mut ini: Ini = Ini::new();
+
+        if let Value::Table(table) = value {
+            let ( sections, global ): ( Table, Table ) = table.into_iter()
+                .partition(|( _, value )| value.as_table().is_some());
+
+            // Keys of the global section have to come before the first section header
+            for ( key, value ) in global {
+                add_value(ini.entry(""), &key, value);
+            }
+
+            for ( name, section ) in sections {
+                let __section: &mut IniSection = ini.entry(&name);
+
+                if let Value::Table(section) = section {
+                    for ( key, value ) in section {
+                        add_value(__section, &key, value);
+                    }
+                }
+            }
+        }
+
+        Ok(ini)
+    }
+
+}
+
+fn add_value(section: &mut IniSection, key: &str, value: Value) {
+    match value {
+        Value::Array(array) => for __value in array {
+            if let Some(__value) = __value.to_flat_string() {
+                section.add(key, &__value);
+            }
+        },
+        Value::Table(_) => for ( path, __value ) in value.flatten() {
+            section.set(&merge::key_path(key, &path), &__value);
+        },
+        value => if let Some(__value) = value.to_flat_string() {
+            section.set(key, &__value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const DOCUMENT: &str = "; Written by legacy tooling
+name = example
+
+[server]
+host = localhost ; not a comment
+port: 8080
+
+# Paths searched in order
+[paths]
+path = /usr/lib
+path = /lib
+";
+
+    fn deserialize(f: &mut IniFormat, s: &str) -> Ini {
+        f.deserialize(s.as_bytes().to_vec(), None).unwrap().0
+    }
+
+    #[test]
+    fn deserialize_sections() {
+        let mut f: IniFormat = IniFormat::new();
+        let ini: Ini = deserialize(&mut f, DOCUMENT);
+
+        assert_eq!(ini.get("", "name"), Some("example"));
+        assert_eq!(ini.get("server", "host"), Some("localhost ; not a comment"));
+        assert_eq!(ini.get("server", "port"), Some("8080"));
+        assert_eq!(ini.sections().map(|( name, _ )| name).collect::<Vec<&str>>(), vec!["", "server", "paths"]);
+    }
+
+    #[test]
+    fn deserialize_duplicates_last_wins() {
+        let mut f: IniFormat = IniFormat::new();
+        assert_eq!(deserialize(&mut f, DOCUMENT).get("paths", "path"), Some("/lib"));
+    }
+
+    #[test]
+    fn deserialize_duplicates_first_wins() {
+        let mut f: IniFormat = IniFormat::new().duplicates(DuplicateKeys::FirstWins);
+        assert_eq!(deserialize(&mut f, DOCUMENT).get("paths", "path"), Some("/usr/lib"));
+    }
+
+    #[test]
+    fn deserialize_duplicates_collect() {
+        let mut f: IniFormat = IniFormat::new().duplicates(DuplicateKeys::Collect);
+        let ini: Ini = deserialize(&mut f, DOCUMENT);
+        assert_eq!(ini.section("paths").unwrap().get_all("path").unwrap(), &["/usr/lib", "/lib"]);
+    }
+
+    #[test]
+    fn deserialize_duplicates_error() {
+        let mut f: IniFormat = IniFormat::new().duplicates(DuplicateKeys::Error);
+
+        match f.deserialize(DOCUMENT.as_bytes().to_vec(), None) {
+            Err(IniError::DuplicateKey { line, .. }) => assert_eq!(line, 11),
+            _ => panic!("Expected a duplicate key error")
+        }
+    }
+
+    #[test]
+    fn deserialize_syntax_error() {
+        let mut f: IniFormat = IniFormat::new();
+
+        match f.deserialize(b"[server]\nhost\n".to_vec(), None) {
+            Err(IniError::Syntax(line)) => assert_eq!(line, 2),
+            _ => panic!("Expected a syntax error")
+        }
+    }
+
+    #[test]
+    fn deserialize_defaults() {
+        let mut f: IniFormat = IniFormat::new();
+        let mut ini: Ini = Ini::new();
+
+        ini.entry("server").set("port", "8080");
+
+        let d: Deserialized<Ini> = f.deserialize(vec![], Some(&ini)).unwrap();
+        assert_eq!(d.0, ini);
+        assert_eq!(d.1, Defaulted::All);
+    }
+
+    #[test]
+    fn deserialize_merge_defaults() {
+        let mut f: IniFormat = IniFormat::new().merge_defaults(true);
+        let mut ini: Ini = Ini::new();
+
+        ini.entry("").set("name", "default");
+        ini.entry("server").set("port", "80");
+        ini.entry("server").set("timeout", "30");
+
+        let d: Deserialized<Ini> = f.deserialize(DOCUMENT.as_bytes().to_vec(), Some(&ini)).unwrap();
+        assert_eq!(d.0.get("", "name"), Some("example"));
+        assert_eq!(d.0.get("server", "port"), Some("8080"));
+        assert_eq!(d.0.get("server", "timeout"), Some("30"));
+        assert_eq!(d.1, Defaulted::Keys(vec![ String::from("server.timeout") ]));
+    }
+
+    #[test]
+    fn value_round_trip() {
+        let mut f: IniFormat = IniFormat::new().duplicates(DuplicateKeys::Collect);
+        let v: Value = f.parse(DOCUMENT.as_bytes().to_vec()).unwrap();
+
+        assert_eq!(v.get("name"), Some(&Value::from("example")));
+        assert_eq!(v.get("server.port"), Some(&Value::from("8080")));
+        assert_eq!(v.get("paths.path"), Some(&Value::Array(vec![ Value::from("/usr/lib"), Value::from("/lib") ])));
+
+        let ini: Ini = f.to_content(v).unwrap();
+        assert_eq!(ini.get("", "name"), Some("example"));
+        assert_eq!(ini.section("paths").unwrap().get_all("path").unwrap(), &["/usr/lib", "/lib"]);
+    }
+
+    #[test]
+    fn serialize_new_document() {
+        let mut f: IniFormat = IniFormat::new();
+        let mut ini: Ini = Ini::new();
+
+        ini.entry("server").set("port", "8080");
+        ini.entry("").set("name", "example");
+
+        assert_eq!(f.serialize(Some(&ini)).unwrap(), b"name = example\n\n[server]\nport = 8080\n".to_vec());
+    }
+
+    #[test]
+    fn serialize_unchanged_preserves_document() {
+        let mut f: IniFormat = IniFormat::new().duplicates(DuplicateKeys::Collect);
+        let ini: Ini = deserialize(&mut f, DOCUMENT);
+        assert_eq!(f.serialize(Some(&ini)).unwrap(), DOCUMENT.as_bytes().to_vec());
+    }
+
+    #[test]
+    fn serialize_changed_preserves_comments() {
+        let mut f: IniFormat = IniFormat::new().duplicates(DuplicateKeys::Collect);
+        let mut ini: Ini = deserialize(&mut f, DOCUMENT);
+
+        ini.entry("server").set("port", "8081");
+        ini.entry("server").set("timeout", "30");
+        ini.entry("paths").add("path", "/opt/lib");
+        ini.entry("cache").set("size", "64");
+
+        let s: String = String::from_utf8(f.serialize(Some(&ini)).unwrap()).unwrap();
+        assert_eq!(s, "; Written by legacy tooling
+name = example
+
+[server]
+host = localhost ; not a comment
+port: 8081
+timeout = 30
+
+# Paths searched in order
+[paths]
+path = /usr/lib
+path = /lib
+path = /opt/lib
+
+[cache]
+size = 64
+");
+    }
+
+    #[test]
+    fn serialize_duplicates_last_wins() {
+        let mut f: IniFormat = IniFormat::new();
+        let mut ini: Ini = deserialize(&mut f, DOCUMENT);
+
+        assert_eq!(f.serialize(Some(&ini)).unwrap(), DOCUMENT.as_bytes().to_vec());
+
+        ini.entry("paths").set("path", "/opt/lib");
+
+        let s: String = String::from_utf8(f.serialize(Some(&ini)).unwrap()).unwrap();
+        assert_eq!(s, DOCUMENT.replace("path = /lib", "path = /opt/lib"));
+        assert_eq!(deserialize(&mut f, &s).get("paths", "path"), Some("/opt/lib"));
+    }
+
+    #[test]
+    fn serialize_duplicates_first_wins() {
+        let mut f: IniFormat = IniFormat::new().duplicates(DuplicateKeys::FirstWins);
+        let mut ini: Ini = deserialize(&mut f, DOCUMENT);
+
+        assert_eq!(f.serialize(Some(&ini)).unwrap(), DOCUMENT.as_bytes().to_vec());
+
+        ini.entry("paths").set("path", "/opt/lib");
+
+        let s: String = String::from_utf8(f.serialize(Some(&ini)).unwrap()).unwrap();
+        assert_eq!(s, DOCUMENT.replace("path = /usr/lib", "path = /opt/lib"));
+        assert_eq!(deserialize(&mut f, &s).get("paths", "path"), Some("/opt/lib"));
+    }
+
+    #[test]
+    fn serialize_removed_section() {
+        let mut f: IniFormat = IniFormat::new().duplicates(DuplicateKeys::Collect);
+        let mut ini: Ini = deserialize(&mut f, DOCUMENT);
+
+        ini.remove_section("paths");
+        ini.entry("server").remove("host");
+
+        let s: String = String::from_utf8(f.serialize(Some(&ini)).unwrap()).unwrap();
+        assert_eq!(s, "; Written by legacy tooling\nname = example\n\n[server]\nport: 8080\n\n# Paths searched in order\n");
+    }
+
+}
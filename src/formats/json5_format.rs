@@ -0,0 +1,187 @@
+use std::marker::PhantomData;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::format::{ Format, StructuredFormat, Deserialized, Defaulted };
+use crate::formats::codec_error::CodecError;
+use crate::formats::empty;
+use crate::formats::merge;
+use crate::value::Value;
+
+/// Format for JSON5 files, (de)serializing content with serde.
+///
+/// Since JSON is valid JSON5, pretty output is written as indented JSON
+pub struct Json5Format<T> {
+    pretty: bool,
+    merge_defaults: bool,
+    content: PhantomData<fn() -> T>
+}
+
+#[allow(clippy::new_without_default)]
+impl<T> Json5Format<T> {
+
+    pub fn new() -> Self {
+        Self {
+            pretty: false,
+            merge_defaults: false,
+            content: PhantomData
+        }
+    }
+
+    /// Whether to serialize with indentation and newlines instead of compactly
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Whether to fill in keys missing from the file with the defaults, instead of only using the defaults for empty files
+    pub fn merge_defaults(mut self, merge_defaults: bool) -> Self {
+        self.merge_defaults = merge_defaults;
+        self
+    }
+
+}
+
+impl<T> Clone for Json5Format<T> {
+
+    fn clone(&self) -> Self {
+        Self {
+            pretty: self.pretty,
+            merge_defaults: self.merge_defaults,
+            content: PhantomData
+        }
+    }
+
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> Format for Json5Format<T> {
+
+    type Content = T;
+    type Defaults = T;
+    type Error = CodecError<json5::Error, serde_json::Error>;
+
+    fn deserialize(&mut self, input: Vec<u8>, defaults: Option<&Self::Defaults>) -> Result<Deserialized<Self::Content>, Self::Error> {
+        if !input.is_empty() {
+            let input: String = String::from_utf8(input).map_err(serde::de::Error::custom)
+                .map_err(CodecError::Decode)?;
+
+            match defaults {
+                Some(__defaults) if self.merge_defaults => {
+                    let mut value: serde_json::Value = json5::from_str(&input).map_err(CodecError::Decode)?;
+                    let mut defaulted: Vec<String> = Vec::new();
+
+                    merge::merge_json(&mut value, serde_json::to_value(__defaults).map_err(CodecError::Encode)?, "", &mut defaulted);
+
+                    let content: T = serde_json::from_value(value).map_err(serde::de::Error::custom)
+                        .map_err(CodecError::Decode)?;
+
+                    Ok(Deserialized(content, Defaulted::from_keys(defaulted)))
+                },
+                _ => Ok(Deserialized(json5::from_str(&input).map_err(CodecError::Decode)?, Defaulted::None))
+            }
+        } else {
+            Ok(Deserialized(match defaults {
+                Some(__defaults) => __defaults.clone(),
+                None => empty::empty().map_err(CodecError::Decode)?
+            }, Defaulted::All))
+        }
+    }
+
+    fn serialize(&mut self, input: Option<&Self::Content>) -> Result<Vec<u8>, Self::Error> {
+        match input {
+            Some(__input) if self.pretty => serde_json::to_vec_pretty(__input).map_err(CodecError::Encode),
+            Some(__input) => serde_json::to_vec(__input).map_err(CodecError::Encode),
+            None => Ok(Vec::new())
+        }
+    }
+
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> StructuredFormat for Json5Format<T> {
+
+    fn parse(&mut self, input: Vec<u8>) -> Result<Value, Self::Error> {
+        if input.is_empty() {
+            return Ok(Value::default());
+        }
+
+        let input: String = String::from_utf8(input).map_err(serde::de::Error::custom)
+            .map_err(CodecError::Decode)?;
+
+        Ok(Value::from(json5::from_str::<serde_json::Value>(&input).map_err(CodecError::Decode)?))
+    }
+
+    fn to_value(&mut self, content: &Self::Content) -> Result<Value, Self::Error> {
+        Ok(Value::from(serde_json::to_value(content).map_err(CodecError::Encode)?))
+    }
+
+    fn to_content(&mut self, value: Value) -> Result<Self::Content, Self::Error> {
+        serde_json::from_value(serde_json::Value::from(value)).map_err(serde::de::Error::custom)
+            .map_err(CodecError::Decode)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+    struct Settings {
+        name: String,
+        port: u16
+    }
+
+    fn settings() -> Settings {
+        Settings {
+            name: String::from("Hello, world!"),
+            port: 8080
+        }
+    }
+
+    #[test]
+    fn deserialize_comments_and_trailing_commas() {
+        let mut f: Json5Format<Settings> = Json5Format::new();
+        let b: Vec<u8> = b"// Server\n{\n    name: 'Hello, world!',\n    port: 0x1F90,\n}\n".to_vec();
+        assert_eq!(f.deserialize(b, None).unwrap().0, settings());
+    }
+
+    #[test]
+    fn deserialize_defaults() {
+        let mut f: Json5Format<Settings> = Json5Format::new();
+        let d: Deserialized<Settings> = f.deserialize(vec![], Some(&settings())).unwrap();
+        assert_eq!(d.0, settings());
+        assert_eq!(d.1, Defaulted::All);
+    }
+
+    #[test]
+    fn deserialize_merge_defaults() {
+        let mut f: Json5Format<Settings> = Json5Format::new().merge_defaults(true);
+        let d: Deserialized<Settings> = f.deserialize(b"{ port: 80 }".to_vec(), Some(&settings())).unwrap();
+        assert_eq!(d.0, Settings { port: 80, ..settings() });
+        assert_eq!(d.1, Defaulted::Keys(vec![ String::from("name") ]));
+    }
+
+    #[test]
+    fn deserialize_invalid_json5() {
+        let mut f: Json5Format<Settings> = Json5Format::new();
+        assert!(f.deserialize(b"{ name: ".to_vec(), None).is_err());
+        assert!(f.deserialize(vec![0xff], None).is_err());
+    }
+
+    #[test]
+    fn serialize_compact() {
+        let mut f: Json5Format<Settings> = Json5Format::new();
+        assert_eq!(f.serialize(Some(&settings())).unwrap(), br#"{"name":"Hello, world!","port":8080}"#.to_vec());
+    }
+
+    #[test]
+    fn serialize_pretty() {
+        let mut f: Json5Format<Settings> = Json5Format::new().pretty(true);
+        let s: String = String::from_utf8(f.serialize(Some(&settings())).unwrap()).unwrap();
+        assert_eq!(s, "{\n  \"name\": \"Hello, world!\",\n  \"port\": 8080\n}");
+    }
+
+}
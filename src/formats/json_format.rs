@@ -0,0 +1,195 @@
+use std::marker::PhantomData;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::format::{ Format, StructuredFormat, Deserialized, Defaulted };
+use crate::formats::merge;
+use crate::formats::empty;
+use crate::value::Value;
+
+/// Format for JSON files, (de)serializing content with serde
+pub struct JsonFormat<T> {
+    pretty: bool,
+    merge_defaults: bool,
+    content: PhantomData<fn() -> T>
+}
+
+#[allow(clippy::new_without_default)]
+impl<T> JsonFormat<T> {
+
+    pub fn new() -> Self {
+        Self {
+            pretty: false,
+            merge_defaults: false,
+            content: PhantomData
+        }
+    }
+
+    /// Whether to serialize with indentation and newlines instead of compactly
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Whether to fill in keys missing from the file with the defaults, instead of only using the defaults for empty files
+    pub fn merge_defaults(mut self, merge_defaults: bool) -> Self {
+        self.merge_defaults = merge_defaults;
+        self
+    }
+
+}
+
+impl<T> Clone for JsonFormat<T> {
+
+    fn clone(&self) -> Self {
+        Self {
+            pretty: self.pretty,
+            merge_defaults: self.merge_defaults,
+            content: PhantomData
+        }
+    }
+
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> Format for JsonFormat<T> {
+
+    type Content = T;
+    type Defaults = T;
+    type Error = serde_json::Error;
+
+    fn deserialize(&mut self, input: Vec<u8>, defaults: Option<&Self::Defaults>) -> Result<Deserialized<Self::Content>, Self::Error> {
+        if !input.is_empty() {
+            match defaults {
+                Some(__defaults) if self.merge_defaults => {
+                    let mut value: serde_json::Value = serde_json::from_slice(&input)?;
+                    let mut defaulted: Vec<String> = Vec::new();
+
+                    merge::merge_json(&mut value, serde_json::to_value(__defaults)?, "", &mut defaulted);
+
+                    Ok(Deserialized(serde_json::from_value(value)?, Defaulted::from_keys(defaulted)))
+                },
+                _ => Ok(Deserialized(serde_json::from_slice(&input)?, Defaulted::None))
+            }
+        } else {
+            Ok(Deserialized(match defaults {
+                Some(__defaults) => __defaults.clone(),
+                None => empty::empty()?
+            }, Defaulted::All))
+        }
+    }
+
+    fn serialize(&mut self, input: Option<&Self::Content>) -> Result<Vec<u8>, Self::Error> {
+        match input {
+            Some(__input) if self.pretty => serde_json::to_vec_pretty(__input),
+            Some(__input) => serde_json::to_vec(__input),
+            None => Ok(Vec::new())
+        }
+    }
+
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> StructuredFormat for JsonFormat<T> {
+
+    fn parse(&mut self, input: Vec<u8>) -> Result<Value, Self::Error> {
+        if input.is_empty() {
+            return Ok(Value::default());
+        }
+
+        Ok(Value::from(serde_json::from_slice::<serde_json::Value>(&input)?))
+    }
+
+    fn to_value(&mut self, content: &Self::Content) -> Result<Value, Self::Error> {
+        Ok(Value::from(serde_json::to_value(content)?))
+    }
+
+    fn to_content(&mut self, value: Value) -> Result<Self::Content, Self::Error> {
+        serde_json::from_value(serde_json::Value::from(value))
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+    struct Settings {
+        name: String,
+        port: u16
+    }
+
+    fn settings() -> Settings {
+        Settings {
+            name: String::from("Hello, world!"),
+            port: 8080
+        }
+    }
+
+    #[test]
+    fn deserialize_bytes_to_struct() {
+        let mut f: JsonFormat<Settings> = JsonFormat::new();
+        let b: Vec<u8> = br#"{ "name": "Hello, world!", "port": 8080 }"#.to_vec();
+        assert_eq!(f.deserialize(b, None).unwrap().0, settings());
+    }
+
+    #[test]
+    fn deserialize_defaults() {
+        let mut f: JsonFormat<Settings> = JsonFormat::new();
+        let d: Deserialized<Settings> = f.deserialize(vec![], Some(&settings())).unwrap();
+        assert_eq!(d.0, settings());
+        assert_eq!(d.1, Defaulted::All);
+    }
+
+    #[test]
+    fn deserialize_merge_defaults() {
+        let mut f: JsonFormat<Settings> = JsonFormat::new().merge_defaults(true);
+        let d: Deserialized<Settings> = f.deserialize(br#"{ "port": 80 }"#.to_vec(), Some(&settings())).unwrap();
+        assert_eq!(d.0, Settings { port: 80, ..settings() });
+        assert_eq!(d.1, Defaulted::Keys(vec![ String::from("name") ]));
+    }
+
+    #[test]
+    fn value_round_trip() {
+        let mut f: JsonFormat<Settings> = JsonFormat::new();
+        let v: Value = f.parse(br#"{ "port": 80 }"#.to_vec()).unwrap();
+        assert_eq!(v.get("port"), Some(&Value::Integer(80)));
+
+        let mut v: Value = f.to_value(&settings()).unwrap();
+        v.set("port", Value::Integer(80));
+        assert_eq!(f.to_content(v).unwrap(), Settings { port: 80, ..settings() });
+    }
+
+    #[test]
+    fn deserialize_empty_without_defaults() {
+        let mut f: JsonFormat<Option<Settings>> = JsonFormat::new();
+        assert_eq!(f.deserialize(vec![], None).unwrap().0, None);
+
+        let mut f: JsonFormat<serde_json::Value> = JsonFormat::new();
+        let d: Deserialized<serde_json::Value> = f.deserialize(vec![], None).unwrap();
+        assert_eq!(d.0, serde_json::json!({}));
+        assert_eq!(d.1, Defaulted::All);
+    }
+
+    #[test]
+    fn deserialize_invalid_json() {
+        let mut f: JsonFormat<Settings> = JsonFormat::new();
+        assert!(f.deserialize(b"{ \"name\": ".to_vec(), None).is_err());
+    }
+
+    #[test]
+    fn serialize_compact() {
+        let mut f: JsonFormat<Settings> = JsonFormat::new();
+        assert_eq!(f.serialize(Some(&settings())).unwrap(), br#"{"name":"Hello, world!","port":8080}"#.to_vec());
+    }
+
+    #[test]
+    fn serialize_pretty() {
+        let mut f: JsonFormat<Settings> = JsonFormat::new().pretty(true);
+        let s: String = String::from_utf8(f.serialize(Some(&settings())).unwrap()).unwrap();
+        assert_eq!(s, "{\n  \"name\": \"Hello, world!\",\n  \"port\": 8080\n}");
+    }
+
+}
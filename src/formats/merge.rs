@@ -0,0 +1,76 @@
+//! Filling in keys missing from deserialized content with the keys of it's defaults,
+//! for formats with merge defaults enabled
+
+/// Joins `key` onto the path of it's parent, as in `database.port`
+pub(crate) fn key_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        String::from(key)
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+/// Inserts the keys of `defaults` missing from `value`, recursing into objects present in both.
+/// Paths of inserted keys are pushed to `defaulted`
+#[cfg(any(feature = "json", feature = "json5", feature = "ron", feature = "msgpack", feature = "cbor"))]
+pub(crate) fn merge_json(value: &mut serde_json::Value, defaults: serde_json::Value, path: &str, defaulted: &mut Vec<String>) {
+    if let ( serde_json::Value::Object(object), serde_json::Value::Object(defaults) ) = ( value, defaults ) {
+        for ( key, default ) in defaults {
+            let key_path: String = key_path(path, &key);
+
+            match object.get_mut(&key) {
+                Some(value) => merge_json(value, default, &key_path, defaulted),
+                None => {
+                    object.insert(key, default);
+                    defaulted.push(key_path);
+                }
+            }
+        }
+    }
+}
+
+/// Same as `merge_json`, for YAML mappings
+#[cfg(feature = "yaml")]
+pub(crate) fn merge_yaml(value: &mut serde_yaml::Value, defaults: serde_yaml::Value, path: &str, defaulted: &mut Vec<String>) {
+    if let ( serde_yaml::Value::Mapping(mapping), serde_yaml::Value::Mapping(defaults) ) = ( value, defaults ) {
+        for ( key, default ) in defaults {
+            let key_path: String = match &key {
+                serde_yaml::Value::String(key) => key_path(path, key),
+                key => key_path(path, serde_yaml::to_string(key).unwrap_or_default().trim_end())
+            };
+
+            match mapping.get_mut(&key) {
+                Some(value) => merge_yaml(value, default, &key_path, defaulted),
+                None => {
+                    mapping.insert(key, default);
+                    defaulted.push(key_path);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn nested_key_path() {
+        assert_eq!(key_path("", "database"), "database");
+        assert_eq!(key_path("database", "port"), "database.port");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn merge_nested_json() {
+        let mut v: serde_json::Value = serde_json::json!({ "name": "a", "database": { "host": "b" } });
+        let mut d: Vec<String> = Vec::new();
+
+        merge_json(&mut v, serde_json::json!({ "name": "c", "database": { "host": "d", "port": 1 }, "debug": false }), "", &mut d);
+
+        assert_eq!(v, serde_json::json!({ "name": "a", "database": { "host": "b", "port": 1 }, "debug": false }));
+        assert_eq!(d, vec![ "database.port", "debug" ]);
+    }
+
+}
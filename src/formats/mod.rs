@@ -1 +1,41 @@
-pub mod string_format;
\ No newline at end of file
+pub mod string_format;
+
+pub mod merge;
+
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml", feature = "msgpack", feature = "cbor", feature = "bincode", feature = "ron", feature = "json5"))]
+pub mod empty;
+
+#[cfg(feature = "json")]
+pub mod json_format;
+
+#[cfg(feature = "toml")]
+pub mod toml_format;
+
+#[cfg(feature = "yaml")]
+pub mod yaml_format;
+
+pub mod ini_format;
+
+pub mod env_file_format;
+
+pub mod properties_format;
+
+#[cfg(any(feature = "msgpack", feature = "cbor", feature = "bincode", feature = "ron", feature = "json5"))]
+pub mod codec_error;
+
+#[cfg(feature = "msgpack")]
+pub mod msgpack_format;
+
+#[cfg(feature = "cbor")]
+pub mod cbor_format;
+
+#[cfg(feature = "bincode")]
+pub mod bincode_format;
+
+#[cfg(feature = "ron")]
+pub mod ron_format;
+
+#[cfg(feature = "json5")]
+pub mod json5_format;
+
+pub mod any_format;
@@ -0,0 +1,474 @@
+use std::string::FromUtf8Error;
+use std::error::Error;
+use std::fmt;
+
+use crate::format::{ Format, StructuredFormat, Deserialized, Defaulted };
+use crate::value::{ Value, Table };
+
+/// Ordered properties of a .properties file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Properties {
+    entries: Vec<(String, String)>
+}
+
+impl Properties {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.iter()
+            .find(|( __key, _ )| __key == key)
+            .map(|( _, value )| value.as_str())
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Sets `key` to `value`, keeping it's position if it already exists
+    pub fn set(&mut self, key: &str, value: &str) {
+        match self.entries.iter_mut().find(|( __key, _ )| __key == key) {
+            Some(( _, __value )) => *__value = String::from(value),
+            None => self.entries.push(( String::from(key), String::from(value) ))
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let index: usize = self.entries.iter().position(|( __key, _ )| __key == key)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|( key, value )| ( key.as_str(), value.as_str() ))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+}
+
+/// Character encoding of a .properties file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// ISO-8859-1, characters outside of it are written as `\uXXXX` escapes
+    Latin1,
+    Utf8
+}
+
+/// Error returned by `PropertiesFormat`
+#[derive(Debug)]
+pub enum PropertiesError {
+    /// The file is not valid UTF-8
+    Utf8(FromUtf8Error),
+    /// `\u` escape at the line isn't followed by four hexadecimal digits, or is half of a surrogate pair
+    Unicode(usize)
+}
+
+impl fmt::Display for PropertiesError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PropertiesError::Utf8(err) => err.fmt(f),
+            PropertiesError::Unicode(line) => write!(f, "line {}: malformed \\uXXXX escape", line)
+        }
+    }
+
+}
+
+impl Error for PropertiesError {
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PropertiesError::Utf8(err) => Some(err),
+            _ => None
+        }
+    }
+
+}
+
+/// Format for Java .properties files.
+///
+/// Keys are separated from values by `=`, `:` or whitespace, lines ending in `\` continue on the next line
+/// and `#` or `!` start comments, as read by `java.util.Properties`
+#[derive(Clone)]
+pub struct PropertiesFormat {
+    encoding: Encoding,
+    merge_defaults: bool
+}
+
+#[allow(clippy::new_without_default)]
+impl PropertiesFormat {
+
+    pub fn new() -> Self {
+        Self {
+            encoding: Encoding::Latin1,
+            merge_defaults: false
+        }
+    }
+
+    /// Character encoding of the file, `Latin1` by default
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Whether to fill in keys missing from the file with the defaults, instead of only using the defaults for empty files
+    pub fn merge_defaults(mut self, merge_defaults: bool) -> Self {
+        self.merge_defaults = merge_defaults;
+        self
+    }
+
+    fn decode(&self, input: Vec<u8>) -> Result<String, PropertiesError> {
+        match self.encoding {
+            Encoding::Latin1 => Ok(input.into_iter().map(char::from).collect()),
+            Encoding::Utf8 => String::from_utf8(input).map_err(PropertiesError::Utf8)
+        }
+    }
+
+    fn escape(&self, output: &mut String, input: &str, key: bool) {
+        for ( i, c ) in input.chars().enumerate() {
+            match c {
+                '\\' => output.push_str("\\\\"),
+                '\t' => output.push_str("\\t"),
+                '\n' => output.push_str("\\n"),
+                '\r' => output.push_str("\\r"),
+                '\x0c' => output.push_str("\\f"),
+                ' ' if key || i == 0 => output.push_str("\\ "),
+                '=' | ':' | '#' | '!' if key => {
+                    output.push('\\');
+                    output.push(c);
+                },
+                c if c < ' ' || (self.encoding == Encoding::Latin1 && c > '~') => {
+                    let mut units: [u16; 2] = [0; 2];
+
+                    for unit in c.encode_utf16(&mut units) {
+                        output.push_str(&format!("\\u{:04X}", unit));
+                    }
+                },
+                c => output.push(c)
+            }
+        }
+    }
+
+}
+
+/// Joins natural lines ending in an odd number of backslashes into logical lines,
+/// along with the line number they start at
+fn logical_lines(input: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+
+    for ( i, line ) in input.lines().enumerate() {
+        let line: &str = match current {
+            Some(_) => line.trim_start_matches([' ', '\t', '\x0c']),
+            None => {
+                let trimmed: &str = line.trim_start_matches([' ', '\t', '\x0c']);
+
+                if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('!') {
+                    continue;
+                }
+
+                trimmed
+            }
+        };
+        let backslashes: usize = line.len() - line.trim_end_matches('\\').len();
+        let ( start, mut logical ) = current.take().unwrap_or(( i + 1, String::new() ));
+
+        if backslashes % 2 == 1 {
+            logical.push_str(&line[..line.len() - 1]);
+            current = Some(( start, logical ));
+        } else {
+            logical.push_str(line);
+            lines.push(( start, logical ));
+        }
+    }
+
+    if let Some(line) = current {
+        lines.push(line);
+    }
+
+    lines
+}
+
+fn unescape(input: &str, line: usize) -> Result<String, PropertiesError> {
+    let mut output: String = String::new();
+    let mut chars = input.chars();
+    let mut high_surrogate: Option<u32> = None;
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            if high_surrogate.is_some() {
+                return Err(PropertiesError::Unicode(line));
+            }
+
+            output.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                let code: u32 = match u32::from_str_radix(&hex, 16) {
+                    Ok(code) if hex.len() == 4 => code,
+                    _ => return Err(PropertiesError::Unicode(line))
+                };
+
+                // Characters outside of the basic multilingual plane are written as UTF-16 surrogate pairs,
+                // the low surrogate escaped right after the high one
+                if (0xd800..0xdc00).contains(&code) && high_surrogate.is_none() {
+                    high_surrogate = Some(code);
+                    continue;
+                }
+
+                let code: u32 = match high_surrogate.take() {
+                    Some(high) if (0xdc00..0xe000).contains(&code) => 0x10000 + ((high - 0xd800) << 10) + (code - 0xdc00),
+                    Some(_) => return Err(PropertiesError::Unicode(line)),
+                    None => code
+                };
+
+                output.push(char::from_u32(code).ok_or(PropertiesError::Unicode(line))?);
+            },
+            _ if high_surrogate.is_some() => return Err(PropertiesError::Unicode(line)),
+            Some('t') => output.push('\t'),
+            Some('n') => output.push('\n'),
+            Some('r') => output.push('\r'),
+            Some('f') => output.push('\x0c'),
+            Some(c) => output.push(c),
+            None => ()
+        }
+    }
+
+    match high_surrogate {
+        Some(_) => Err(PropertiesError::Unicode(line)),
+        None => Ok(output)
+    }
+}
+
+fn parse(input: &str) -> Result<Properties, PropertiesError> {
+    let mut properties: Properties = Properties::new();
+
+    for ( line, logical ) in logical_lines(input) {
+        let mut key_end: usize = logical.len();
+        let mut escaped: bool = false;
+
+        for ( i, c ) in logical.char_indices() {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '=' || c == ':' || c == ' ' || c == '\t' || c == '\x0c' {
+                key_end = i;
+                break;
+            }
+        }
+
+        let rest: &str = logical[key_end..].trim_start_matches([' ', '\t', '\x0c']);
+        let rest: &str = rest.strip_prefix(['=', ':']).unwrap_or(rest);
+        let value: &str = rest.trim_start_matches([' ', '\t', '\x0c']);
+
+        properties.set(&unescape(&logical[..key_end], line)?, &unescape(value, line)?);
+    }
+
+    Ok(properties)
+}
+
+/// Sets the properties of `defaults` missing from `properties`, returning their keys
+fn fill(properties: &mut Properties, defaults: &Properties) -> Vec<String> {
+    let mut defaulted: Vec<String> = Vec::new();
+
+    for ( key, value ) in defaults.iter() {
+        if !properties.contains_key(key) {
+            properties.set(key, value);
+            defaulted.push(String::from(key));
+        }
+    }
+
+    defaulted
+}
+
+impl Format for PropertiesFormat {
+
+    type Content = Properties;
+    type Defaults = Properties;
+    type Error = PropertiesError;
+
+    fn deserialize(&mut self, input: Vec<u8>, defaults: Option<&Self::Defaults>) -> Result<Deserialized<Self::Content>, Self::Error> {
+        if !input.is_empty() {
+            let mut properties: Properties = parse(&self.decode(input)?)?;
+
+            match defaults {
+                Some(__defaults) if self.merge_defaults => {
+                    let defaulted: Vec<String> = fill(&mut properties, __defaults);
+                    Ok(Deserialized(properties, Defaulted::from_keys(defaulted)))
+                },
+                _ => Ok(Deserialized(properties, Defaulted::None))
+            }
+        } else {
+            Ok(Deserialized(match defaults {
+                Some(__defaults) => __defaults.clone(),
+                None => Properties::new()
+            }, Defaulted::All))
+        }
+    }
+
+    fn serialize(&mut self, input: Option<&Self::Content>) -> Result<Vec<u8>, Self::Error> {
+        let mut output: String = String::new();
+
+        if let Some(__input) = input {
+            for ( key, value ) in __input.iter() {
+                self.escape(&mut output, key, true);
+                output.push('=');
+                self.escape(&mut output, value, false);
+                output.push('\n');
+            }
+        }
+
+        // With Latin1 every character outside of ASCII is escaped,
+        // so the UTF-8 bytes are valid in either encoding
+        Ok(output.into_bytes())
+    }
+
+}
+
+/// Properties become strings at the top level, nested tables are flattened into keys like `database.port`
+impl StructuredFormat for PropertiesFormat {
+
+    fn parse(&mut self, input: Vec<u8>) -> Result<Value, Self::Error> {
+        let properties: Properties = parse(&self.decode(input)?)?;
+        self.to_value(&properties)
+    }
+
+    fn to_value(&mut self, content: &Self::Content) -> Result<Value, Self::Error> {
+        Ok(Value::Table(content.iter()
+            .map(|( key, value )| ( String::from(key), Value::from(value) ))
+            .collect::<Table>()))
+    }
+
+    fn to_content(&mut self, value: Value) -> Result<Self::Content, Self::Error> {
+        let mut properties: Properties = Properties::new();
+
+        for ( key, __value ) in value.flatten() {
+            properties.set(&key, &__value);
+        }
+
+        Ok(properties)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn deserialize(f: &mut PropertiesFormat, b: &[u8]) -> Properties {
+        f.deserialize(b.to_vec(), None).unwrap().0
+    }
+
+    #[test]
+    fn deserialize_separators() {
+        let mut f: PropertiesFormat = PropertiesFormat::new();
+        let p: Properties = deserialize(&mut f, b"# Comment\n! Comment\na=1\nb : 2\nc 3\n  d=\n");
+
+        assert_eq!(p.get("a"), Some("1"));
+        assert_eq!(p.get("b"), Some("2"));
+        assert_eq!(p.get("c"), Some("3"));
+        assert_eq!(p.get("d"), Some(""));
+        assert_eq!(p.len(), 4);
+    }
+
+    #[test]
+    fn deserialize_continuation() {
+        let mut f: PropertiesFormat = PropertiesFormat::new();
+        let p: Properties = deserialize(&mut f, b"fruits = apple, \\\n         banana\npath = C:\\\\\nnext = 1\n");
+
+        assert_eq!(p.get("fruits"), Some("apple, banana"));
+        assert_eq!(p.get("path"), Some("C:\\"));
+        assert_eq!(p.get("next"), Some("1"));
+    }
+
+    #[test]
+    fn deserialize_escapes() {
+        let mut f: PropertiesFormat = PropertiesFormat::new();
+        let p: Properties = deserialize(&mut f, b"key\\ with\\=sep = tab\\there\ngreeting = \\u3053\\u3093\nemoji = \\uD83D\\uDE00\n");
+
+        assert_eq!(p.get("key with=sep"), Some("tab\there"));
+        assert_eq!(p.get("greeting"), Some("\u{3053}\u{3093}"));
+        assert_eq!(p.get("emoji"), Some("\u{1F600}"));
+    }
+
+    #[test]
+    fn deserialize_malformed_unicode() {
+        let mut f: PropertiesFormat = PropertiesFormat::new();
+
+        match f.deserialize(b"a=1\nb=\\u12G4\n".to_vec(), None) {
+            Err(PropertiesError::Unicode(line)) => assert_eq!(line, 2),
+            _ => panic!("Expected a unicode error")
+        }
+    }
+
+    #[test]
+    fn deserialize_unpaired_surrogate() {
+        let mut f: PropertiesFormat = PropertiesFormat::new();
+
+        for input in [ &b"a=\\uD83Dabc\\uDE00\n"[..], b"a=\\uD83D\n", b"a=\\uD83D\\u0041\n", b"a=\\uDE00\n" ] {
+            match f.deserialize(input.to_vec(), None) {
+                Err(PropertiesError::Unicode(line)) => assert_eq!(line, 1),
+                _ => panic!("Expected a unicode error")
+            }
+        }
+    }
+
+    #[test]
+    fn deserialize_encodings() {
+        let mut f: PropertiesFormat = PropertiesFormat::new();
+        assert_eq!(deserialize(&mut f, b"name=caf\xe9\n").get("name"), Some("caf\u{e9}"));
+
+        let mut f: PropertiesFormat = PropertiesFormat::new().encoding(Encoding::Utf8);
+        assert_eq!(deserialize(&mut f, "name=caf\u{e9}\n".as_bytes()).get("name"), Some("caf\u{e9}"));
+    }
+
+    #[test]
+    fn deserialize_merge_defaults() {
+        let mut f: PropertiesFormat = PropertiesFormat::new().merge_defaults(true);
+        let mut p: Properties = Properties::new();
+
+        p.set("host", "localhost");
+        p.set("port", "80");
+
+        let d: Deserialized<Properties> = f.deserialize(b"port=8080\n".to_vec(), Some(&p)).unwrap();
+        assert_eq!(d.0.get("host"), Some("localhost"));
+        assert_eq!(d.0.get("port"), Some("8080"));
+        assert_eq!(d.1, Defaulted::Keys(vec![ String::from("host") ]));
+    }
+
+    #[test]
+    fn serialize_latin1() {
+        let mut f: PropertiesFormat = PropertiesFormat::new();
+        let mut p: Properties = Properties::new();
+
+        p.set("key with=sep", " caf\u{e9}\n\u{1F600}");
+
+        let b: Vec<u8> = f.serialize(Some(&p)).unwrap();
+        assert_eq!(b, b"key\\ with\\=sep=\\ caf\\u00E9\\n\\uD83D\\uDE00\n".to_vec());
+        assert_eq!(f.deserialize(b, None).unwrap().0, p);
+    }
+
+    #[test]
+    fn serialize_utf8() {
+        let mut f: PropertiesFormat = PropertiesFormat::new().encoding(Encoding::Utf8);
+        let mut p: Properties = Properties::new();
+
+        p.set("name", "caf\u{e9}");
+
+        assert_eq!(f.serialize(Some(&p)).unwrap(), "name=caf\u{e9}\n".as_bytes().to_vec());
+    }
+
+}
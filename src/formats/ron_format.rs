@@ -0,0 +1,245 @@
+use std::marker::PhantomData;
+
+use serde::{ Serialize, Deserialize };
+use serde::de::DeserializeOwned;
+use ron::ser::PrettyConfig;
+use ron::value::Number;
+use ron::error::{ SpannedError, Span, Position };
+
+use crate::format::{ Format, StructuredFormat, Deserialized, Defaulted };
+use crate::formats::codec_error::CodecError;
+use crate::formats::empty;
+use crate::formats::merge;
+use crate::value::{ Value, Table };
+
+/// Format for RON (Rusty Object Notation) files, (de)serializing content with serde.
+///
+/// Merging defaults and overriding keys go through untyped values, where enum variants can't be told apart from their contents,
+/// so those are only supported for content without enums
+pub struct RonFormat<T> {
+    pretty: Option<PrettyConfig>,
+    merge_defaults: bool,
+    content: PhantomData<fn() -> T>
+}
+
+#[allow(clippy::new_without_default)]
+impl<T> RonFormat<T> {
+
+    pub fn new() -> Self {
+        Self {
+            pretty: None,
+            merge_defaults: false,
+            content: PhantomData
+        }
+    }
+
+    /// Serialize with indentation and newlines as configured instead of compactly
+    pub fn pretty(mut self, pretty: PrettyConfig) -> Self {
+        self.pretty = Some(pretty);
+        self
+    }
+
+    /// Whether to fill in keys missing from the file with the defaults, instead of only using the defaults for empty files
+    pub fn merge_defaults(mut self, merge_defaults: bool) -> Self {
+        self.merge_defaults = merge_defaults;
+        self
+    }
+
+}
+
+impl<T> Clone for RonFormat<T> {
+
+    fn clone(&self) -> Self {
+        Self {
+            pretty: self.pretty.clone(),
+            merge_defaults: self.merge_defaults,
+            content: PhantomData
+        }
+    }
+
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> Format for RonFormat<T> {
+
+    type Content = T;
+    type Defaults = T;
+    type Error = CodecError<SpannedError, ron::Error>;
+
+    fn deserialize(&mut self, input: Vec<u8>, defaults: Option<&Self::Defaults>) -> Result<Deserialized<Self::Content>, Self::Error> {
+        if !input.is_empty() {
+            match defaults {
+                Some(__defaults) if self.merge_defaults => {
+                    let mut value: serde_json::Value = serde_json::Value::from(self.parse(input)?);
+                    let mut defaulted: Vec<String> = Vec::new();
+
+                    merge::merge_json(&mut value, serde_json::to_value(__defaults).map_err(encode_error)?, "", &mut defaulted);
+
+                    Ok(Deserialized(serde_json::from_value(value).map_err(decode_error)?, Defaulted::from_keys(defaulted)))
+                },
+                _ => Ok(Deserialized(ron::de::from_bytes(&input).map_err(CodecError::Decode)?, Defaulted::None))
+            }
+        } else {
+            Ok(Deserialized(match defaults {
+                Some(__defaults) => __defaults.clone(),
+                None => empty::empty().map_err(unspanned)
+                    .map_err(CodecError::Decode)?
+            }, Defaulted::All))
+        }
+    }
+
+    fn serialize(&mut self, input: Option<&Self::Content>) -> Result<Vec<u8>, Self::Error> {
+        let output: String = match ( input, &self.pretty ) {
+            ( Some(__input), Some(pretty) ) => ron::ser::to_string_pretty(__input, pretty.clone()).map_err(CodecError::Encode)?,
+            ( Some(__input), None ) => ron::ser::to_string(__input).map_err(CodecError::Encode)?,
+            ( None, _ ) => String::new()
+        };
+
+        Ok(output.into_bytes())
+    }
+
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> StructuredFormat for RonFormat<T> {
+
+    fn parse(&mut self, input: Vec<u8>) -> Result<Value, Self::Error> {
+        if input.is_empty() {
+            return Ok(Value::default());
+        }
+
+        Ok(from_ron(ron::de::from_bytes(&input).map_err(CodecError::Decode)?))
+    }
+
+    fn to_value(&mut self, content: &Self::Content) -> Result<Value, Self::Error> {
+        Ok(Value::from(serde_json::to_value(content).map_err(encode_error)?))
+    }
+
+    fn to_content(&mut self, value: Value) -> Result<Self::Content, Self::Error> {
+        serde_json::from_value(serde_json::Value::from(value)).map_err(decode_error)
+    }
+
+}
+
+/// Error of content that isn't read from a file, so it has no position in one
+fn unspanned(code: ron::Error) -> SpannedError {
+    SpannedError {
+        code,
+        span: Span {
+            start: Position { line: 1, col: 1 },
+            end: Position { line: 1, col: 1 }
+        }
+    }
+}
+
+fn decode_error(err: serde_json::Error) -> CodecError<SpannedError, ron::Error> {
+    CodecError::Decode(unspanned(ron::Error::Message(err.to_string())))
+}
+
+fn encode_error(err: serde_json::Error) -> CodecError<SpannedError, ron::Error> {
+    CodecError::Encode(ron::Error::Message(err.to_string()))
+}
+
+fn from_ron(value: ron::Value) -> Value {
+    match value {
+        ron::Value::Unit | ron::Value::Option(None) => Value::Null,
+        ron::Value::Option(Some(value)) => from_ron(*value),
+        ron::Value::Bool(value) => Value::Bool(value),
+        ron::Value::Char(value) => Value::String(value.to_string()),
+        ron::Value::Number(number @ ( Number::F32(_) | Number::F64(_) )) => Value::Float(number.into_f64()),
+        ron::Value::Number(number) => match i64::deserialize(ron::Value::Number(number)) {
+            Ok(value) => Value::Integer(value),
+            Err(_) => Value::Float(number.into_f64())
+        },
+        ron::Value::String(value) => Value::String(value),
+        ron::Value::Bytes(bytes) => Value::Array(bytes.into_iter().map(|byte| Value::Integer(i64::from(byte))).collect()),
+        ron::Value::Seq(seq) => Value::Array(seq.into_iter().map(from_ron).collect()),
+        ron::Value::Map(map) => Value::Table(map.into_iter()
+            .map(|( key, value )| {
+                let key: String = match key {
+                    ron::Value::String(key) => key,
+                    key => ron::ser::to_string(&key).unwrap_or_default()
+                };
+
+                ( key, from_ron(value) )
+            })
+            .collect::<Table>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+    struct Window {
+        title: String,
+        size: (u32, u32)
+    }
+
+    fn window() -> Window {
+        Window {
+            title: String::from("Hello, world!"),
+            size: (800, 600)
+        }
+    }
+
+    #[test]
+    fn deserialize_comments_and_trailing_commas() {
+        let mut f: RonFormat<Window> = RonFormat::new();
+        let b: Vec<u8> = b"// Main window\n(\n    title: \"Hello, world!\",\n    size: (800, 600),\n)\n".to_vec();
+        assert_eq!(f.deserialize(b, None).unwrap().0, window());
+    }
+
+    #[test]
+    fn deserialize_defaults() {
+        let mut f: RonFormat<Window> = RonFormat::new();
+        let d: Deserialized<Window> = f.deserialize(vec![], Some(&window())).unwrap();
+        assert_eq!(d.0, window());
+        assert_eq!(d.1, Defaulted::All);
+    }
+
+    #[test]
+    fn deserialize_merge_defaults() {
+        let mut f: RonFormat<Window> = RonFormat::new().merge_defaults(true);
+        let d: Deserialized<Window> = f.deserialize(b"(size: (1024, 768))".to_vec(), Some(&window())).unwrap();
+        assert_eq!(d.0, Window { size: (1024, 768), ..window() });
+        assert_eq!(d.1, Defaulted::Keys(vec![ String::from("title") ]));
+    }
+
+    #[test]
+    fn value_round_trip() {
+        let mut f: RonFormat<Window> = RonFormat::new();
+        let v: Value = f.parse(b"(title: \"Hello, world!\", size: (1024, 768))".to_vec()).unwrap();
+        assert_eq!(v.get("size"), Some(&Value::Array(vec![ Value::Integer(1024), Value::Integer(768) ])));
+
+        let mut v: Value = f.to_value(&window()).unwrap();
+        v.set("title", Value::from("Goodbye"));
+        assert_eq!(f.to_content(v).unwrap(), Window { title: String::from("Goodbye"), ..window() });
+    }
+
+    #[test]
+    fn deserialize_empty_without_defaults() {
+        let mut f: RonFormat<Option<Window>> = RonFormat::new();
+        assert_eq!(f.deserialize(vec![], None).unwrap().0, None);
+    }
+
+    #[test]
+    fn deserialize_invalid_ron() {
+        let mut f: RonFormat<Window> = RonFormat::new();
+        assert!(f.deserialize(b"(title: ".to_vec(), None).is_err());
+    }
+
+    #[test]
+    fn serialize_compact() {
+        let mut f: RonFormat<Window> = RonFormat::new();
+        assert_eq!(f.serialize(Some(&window())).unwrap(), b"(title:\"Hello, world!\",size:(800,600))".to_vec());
+    }
+
+    #[test]
+    fn serialize_pretty() {
+        let mut f: RonFormat<Window> = RonFormat::new().pretty(PrettyConfig::new().indentor("  "));
+        let s: String = String::from_utf8(f.serialize(Some(&window())).unwrap()).unwrap();
+        assert_eq!(s, "(\n  title: \"Hello, world!\",\n  size: (800, 600),\n)");
+    }
+
+}
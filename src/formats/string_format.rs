@@ -1,4 +1,6 @@
-use crate::format::{ Format, Deserialized };
+use std::string::FromUtf8Error;
+
+use crate::format::{ Format, Deserialized, Defaulted };
 
 #[derive(Clone)]
 pub struct StringFormat;
@@ -16,26 +18,24 @@ impl Format for StringFormat {
 
     type Content = String;
     type Defaults = String;
+    type Error = FromUtf8Error;
 
-    fn deserialize(&mut self, input: Vec<u8>, defaults: Option<&Self::Defaults>) -> Deserialized<Self::Content> {
+    fn deserialize(&mut self, input: Vec<u8>, defaults: Option<&Self::Defaults>) -> Result<Deserialized<Self::Content>, Self::Error> {
         if !input.is_empty() {
-            match String::from_utf8(input) {
-                Ok(__input) => Deserialized(__input, false),
-                Err(err) => panic!(err)
-            }
+            Ok(Deserialized(String::from_utf8(input)?, Defaulted::None))
         } else {
-            Deserialized(match defaults {
+            Ok(Deserialized(match defaults {
                 Some(__defaults) => __defaults.clone(),
                 None => String::new()
-            }, true)
+            }, Defaulted::All))
         }
     }
 
-    fn serialize(&mut self, input: Option<&Self::Content>) -> Vec<u8> {
-        match input {
+    fn serialize(&mut self, input: Option<&Self::Content>) -> Result<Vec<u8>, Self::Error> {
+        Ok(match input {
             Some(__input) => __input.as_bytes().to_vec(),
             None => Vec::new()
-        }
+        })
     }
 
 }
@@ -49,21 +49,27 @@ mod tests {
     fn deserialize_bytes_to_string() {
         let mut f: StringFormat = StringFormat::new();
         let s: String = String::from("Hello, world!");
-        assert_eq!(f.deserialize(s.as_bytes().to_vec(), None).0, s);
+        assert_eq!(f.deserialize(s.as_bytes().to_vec(), None).unwrap().0, s);
     }
 
     #[test]
     fn deserialize_defaults() {
         let mut f: StringFormat = StringFormat::new();
         let s: String = String::from("Hello, world!");
-        assert_eq!(f.deserialize(vec![], Some(&s)).0, s);
+        assert_eq!(f.deserialize(vec![], Some(&s)).unwrap().0, s);
     }
 
     #[test]
     fn serialize_string_to_bytes() {
         let mut f: StringFormat = StringFormat::new();
         let s: String = String::from("Hello, world!");
-        assert_eq!(f.serialize(Some(&s)), s.as_bytes().to_vec());
+        assert_eq!(f.serialize(Some(&s)).unwrap(), s.as_bytes().to_vec());
+    }
+
+    #[test]
+    fn deserialize_invalid_utf8() {
+        let mut f: StringFormat = StringFormat::new();
+        assert!(f.deserialize(vec![0xff, 0xfe], None).is_err());
     }
 
 }
\ No newline at end of file
@@ -0,0 +1,515 @@
+use std::marker::PhantomData;
+use std::str::Utf8Error;
+use std::error::Error;
+use std::fmt;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use toml_edit::{ DocumentMut, Item, Table, Value, ArrayOfTables, Array, InlineTable, TableLike };
+
+use crate::format::{ Format, StructuredFormat, Deserialized, Defaulted };
+use crate::formats::merge;
+use crate::formats::empty;
+use crate::value::{ self, Value as ConfigValue };
+
+/// Error returned by `TomlFormat`
+#[derive(Debug)]
+pub enum TomlFormatError {
+    /// The file is not valid UTF-8
+    Utf8(Utf8Error),
+    /// The file is not valid TOML
+    Parse(toml_edit::TomlError),
+    /// The TOML document doesn't match the content type
+    Deserialize(toml_edit::de::Error),
+    /// The content can't be represented as TOML
+    Serialize(toml_edit::ser::Error)
+}
+
+impl fmt::Display for TomlFormatError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TomlFormatError::Utf8(err) => err.fmt(f),
+            TomlFormatError::Parse(err) => err.fmt(f),
+            TomlFormatError::Deserialize(err) => err.fmt(f),
+            TomlFormatError::Serialize(err) => err.fmt(f)
+        }
+    }
+
+}
+
+impl Error for TomlFormatError {
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TomlFormatError::Utf8(err) => Some(err),
+            TomlFormatError::Parse(err) => Some(err),
+            TomlFormatError::Deserialize(err) => Some(err),
+            TomlFormatError::Serialize(err) => Some(err)
+        }
+    }
+
+}
+
+/// Format for TOML files, (de)serializing content with serde.
+///
+/// The last document read or written is kept, so serializing only edits the values that changed
+/// and leaves comments, whitespace and the order of keys and tables as they were
+pub struct TomlFormat<T> {
+    document: Option<DocumentMut>,
+    merge_defaults: bool,
+    content: PhantomData<fn() -> T>
+}
+
+#[allow(clippy::new_without_default)]
+impl<T> TomlFormat<T> {
+
+    pub fn new() -> Self {
+        Self {
+            document: None,
+            merge_defaults: false,
+            content: PhantomData
+        }
+    }
+
+    /// Whether to fill in keys missing from the file with the defaults, instead of only using the defaults for empty files.
+    ///
+    /// Filled in keys are added to the file the next time it's written
+    pub fn merge_defaults(mut self, merge_defaults: bool) -> Self {
+        self.merge_defaults = merge_defaults;
+        self
+    }
+
+}
+
+impl<T> Clone for TomlFormat<T> {
+
+    fn clone(&self) -> Self {
+        Self {
+            document: self.document.clone(),
+            merge_defaults: self.merge_defaults,
+            content: PhantomData
+        }
+    }
+
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> Format for TomlFormat<T> {
+
+    type Content = T;
+    type Defaults = T;
+    type Error = TomlFormatError;
+
+    fn deserialize(&mut self, input: Vec<u8>, defaults: Option<&Self::Defaults>) -> Result<Deserialized<Self::Content>, Self::Error> {
+        if !input.is_empty() {
+            let input: &str = std::str::from_utf8(&input).map_err(TomlFormatError::Utf8)?;
+            let document: DocumentMut = input.parse().map_err(TomlFormatError::Parse)?;
+            let mut merged: DocumentMut = document.clone();
+            let mut defaulted: Vec<String> = Vec::new();
+
+            if let ( Some(__defaults), true ) = ( defaults, self.merge_defaults ) {
+                let defaults: DocumentMut = toml_edit::ser::to_document(__defaults).map_err(TomlFormatError::Serialize)?;
+                fill_table(merged.as_table_mut(), defaults.as_table(), "", &mut defaulted);
+            }
+
+            let content: T = toml_edit::de::from_document(merged).map_err(TomlFormatError::Deserialize)?;
+
+            self.document = Some(document);
+
+            Ok(Deserialized(content, Defaulted::from_keys(defaulted)))
+        } else {
+            self.document = None;
+
+            Ok(Deserialized(match defaults {
+                Some(__defaults) => __defaults.clone(),
+                None => empty::empty().map_err(TomlFormatError::Deserialize)?
+            }, Defaulted::All))
+        }
+    }
+
+    fn serialize(&mut self, input: Option<&Self::Content>) -> Result<Vec<u8>, Self::Error> {
+        let input: &T = match input {
+            Some(__input) => __input,
+            None => return Ok(Vec::new())
+        };
+        let new: DocumentMut = toml_edit::ser::to_document(input).map_err(TomlFormatError::Serialize)?;
+        let document: DocumentMut = match self.document.take() {
+            Some(mut document) => {
+                merge_table(document.as_table_mut(), new.into_table());
+                document
+            },
+            None => {
+                let mut table: Table = new.into_table();
+
+                make_items(&mut table);
+                table.into()
+            }
+        };
+        let output: Vec<u8> = document.to_string().into_bytes();
+
+        self.document = Some(document);
+
+        Ok(output)
+    }
+
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> StructuredFormat for TomlFormat<T> {
+
+    fn parse(&mut self, input: Vec<u8>) -> Result<ConfigValue, Self::Error> {
+        let input: &str = std::str::from_utf8(&input).map_err(TomlFormatError::Utf8)?;
+        let document: DocumentMut = input.parse().map_err(TomlFormatError::Parse)?;
+
+        Ok(from_table(document.as_table()))
+    }
+
+    fn to_value(&mut self, content: &Self::Content) -> Result<ConfigValue, Self::Error> {
+        let document: DocumentMut = toml_edit::ser::to_document(content).map_err(TomlFormatError::Serialize)?;
+        Ok(from_table(document.as_table()))
+    }
+
+    fn to_content(&mut self, value: ConfigValue) -> Result<Self::Content, Self::Error> {
+        let table: Table = match to_item(value) {
+            Some(Item::Table(table)) => table,
+            _ => Table::new()
+        };
+
+        toml_edit::de::from_document(DocumentMut::from(table)).map_err(TomlFormatError::Deserialize)
+    }
+
+}
+
+fn from_table(table: &dyn TableLike) -> ConfigValue {
+    ConfigValue::Table(table.iter()
+        .filter_map(|( key, item )| Some(( String::from(key), from_item(item)? )))
+        .collect::<value::Table>())
+}
+
+fn from_item(item: &Item) -> Option<ConfigValue> {
+    match item {
+        Item::None => None,
+        Item::Value(value) => Some(from_value(value)),
+        Item::Table(table) => Some(from_table(table)),
+        Item::ArrayOfTables(tables) => Some(ConfigValue::Array(tables.iter().map(|table| from_table(table)).collect()))
+    }
+}
+
+fn from_value(value: &Value) -> ConfigValue {
+    match value {
+        Value::String(value) => ConfigValue::String(value.value().clone()),
+        Value::Integer(value) => ConfigValue::Integer(*value.value()),
+        Value::Float(value) => ConfigValue::Float(*value.value()),
+        Value::Boolean(value) => ConfigValue::Bool(*value.value()),
+        Value::Datetime(value) => ConfigValue::String(value.value().to_string()),
+        Value::Array(array) => ConfigValue::Array(array.iter().map(from_value).collect()),
+        Value::InlineTable(table) => from_table(table)
+    }
+}
+
+/// Tables become standard tables and arrays of only tables become arrays of tables.
+/// TOML has no null, so null values are left out
+fn to_item(value: ConfigValue) -> Option<Item> {
+    match value {
+        ConfigValue::Table(__table) => {
+            let mut table: Table = Table::new();
+
+            for ( key, value ) in __table {
+                if let Some(item) = to_item(value) {
+                    table.insert(&key, item);
+                }
+            }
+
+            Some(Item::Table(table))
+        },
+        ConfigValue::Array(array) if !array.is_empty() && array.iter().all(|value| value.as_table().is_some()) => {
+            let mut tables: ArrayOfTables = ArrayOfTables::new();
+
+            for value in array {
+                if let Some(Item::Table(table)) = to_item(value) {
+                    tables.push(table);
+                }
+            }
+
+            Some(Item::ArrayOfTables(tables))
+        },
+        value => to_value(value).map(Item::Value)
+    }
+}
+
+fn to_value(value: ConfigValue) -> Option<Value> {
+    match value {
+        ConfigValue::Null => None,
+        ConfigValue::Bool(value) => Some(Value::from(value)),
+        ConfigValue::Integer(value) => Some(Value::from(value)),
+        ConfigValue::Float(value) => Some(Value::from(value)),
+        ConfigValue::String(value) => Some(Value::from(value)),
+        ConfigValue::Array(array) => Some(Value::Array(array.into_iter().filter_map(to_value).collect())),
+        ConfigValue::Table(table) => Some(Value::InlineTable(table.into_iter()
+            .filter_map(|( key, value )| Some(( key, to_value(value)? )))
+            .collect()))
+    }
+}
+
+/// Inserts the keys of `defaults` missing from `table`, recursing into tables present in both
+fn fill_table(table: &mut dyn TableLike, defaults: &dyn TableLike, path: &str, defaulted: &mut Vec<String>) {
+    for ( key, default ) in defaults.iter() {
+        let key_path: String = merge::key_path(path, key);
+
+        match table.get_mut(key) {
+            Some(item) => {
+                if let ( Some(item), Some(default) ) = ( item.as_table_like_mut(), default.as_table_like() ) {
+                    fill_table(item, default, &key_path, defaulted);
+                }
+            },
+            None => {
+                table.insert(key, default.clone());
+                defaulted.push(key_path);
+            }
+        }
+    }
+}
+
+/// Updates `old` to hold the values of `new`, keeping the formatting of `old` where values are unchanged
+fn merge_table(old: &mut Table, new: Table) {
+    old.retain(|key, _| new.contains_key(key));
+
+    for ( key, item ) in new {
+        match old.get_mut(&key) {
+            Some(old_item) => merge_item(old_item, item),
+            None => {
+                old.insert(&key, make_item(item));
+            }
+        }
+    }
+}
+
+fn merge_item(old: &mut Item, new: Item) {
+    match old {
+        Item::Table(old_table) => match new.into_table() {
+            Ok(new_table) => merge_table(old_table, new_table),
+            Err(new) => *old = make_item(new)
+        },
+        Item::ArrayOfTables(old_tables) => match new.into_array_of_tables() {
+            Ok(new_tables) => merge_array_of_tables(old_tables, new_tables),
+            Err(new) => *old = make_item(new)
+        },
+        Item::Value(old_value) => match new {
+            Item::Value(new_value) => merge_value(old_value, new_value),
+            new => *old = new
+        },
+        Item::None => *old = make_item(new)
+    }
+}
+
+fn merge_array_of_tables(old: &mut ArrayOfTables, new: ArrayOfTables) {
+    let len: usize = new.len();
+
+    for ( i, mut table ) in new.into_iter().enumerate() {
+        match old.get_mut(i) {
+            Some(old_table) => merge_table(old_table, table),
+            None => {
+                make_items(&mut table);
+                old.push(table);
+            }
+        }
+    }
+
+    while old.len() > len {
+        old.remove(old.len() - 1);
+    }
+}
+
+fn merge_inline_table(old: &mut InlineTable, new: InlineTable) {
+    old.retain(|key, _| new.contains_key(key));
+
+    for ( key, value ) in new {
+        match old.get_mut(&key) {
+            Some(old_value) => merge_value(old_value, value),
+            None => {
+                old.insert(key, value);
+            }
+        }
+    }
+}
+
+fn merge_array(old: &mut Array, new: Array) {
+    let len: usize = new.len();
+
+    for ( i, value ) in new.into_iter().enumerate() {
+        match old.get_mut(i) {
+            Some(old_value) => merge_value(old_value, value),
+            None => old.push_formatted(value)
+        }
+    }
+
+    while old.len() > len {
+        old.remove(old.len() - 1);
+    }
+}
+
+fn merge_value(old: &mut Value, new: Value) {
+    match ( old, new ) {
+        ( Value::InlineTable(old_table), Value::InlineTable(new_table) ) => merge_inline_table(old_table, new_table),
+        ( Value::Array(old_array), Value::Array(new_array) ) => merge_array(old_array, new_array),
+        ( old, new ) => {
+            if !same_value(old, &new) {
+                let decor = old.decor().clone();
+
+                *old = new;
+                *old.decor_mut() = decor;
+            }
+        }
+    }
+}
+
+/// Whether two scalar values are equal, ignoring how they're written
+fn same_value(a: &Value, b: &Value) -> bool {
+    match ( a, b ) {
+        ( Value::String(a), Value::String(b) ) => a.value() == b.value(),
+        ( Value::Integer(a), Value::Integer(b) ) => a.value() == b.value(),
+        ( Value::Float(a), Value::Float(b) ) => a.value() == b.value(),
+        ( Value::Boolean(a), Value::Boolean(b) ) => a.value() == b.value(),
+        ( Value::Datetime(a), Value::Datetime(b) ) => a.value() == b.value(),
+        _ => false
+    }
+}
+
+/// Turns inline tables and arrays of inline tables into standard tables,
+/// the way a newly written document would lay them out
+fn make_item(item: Item) -> Item {
+    let item: Item = match item.into_table() {
+        Ok(mut table) => {
+            make_items(&mut table);
+            return Item::Table(table);
+        },
+        Err(item) => item
+    };
+
+    match item.into_array_of_tables() {
+        Ok(mut tables) => {
+            for table in tables.iter_mut() {
+                make_items(table);
+            }
+
+            Item::ArrayOfTables(tables)
+        },
+        Err(item) => item
+    }
+}
+
+fn make_items(table: &mut Table) {
+    for ( _, item ) in table.iter_mut() {
+        *item = make_item(std::mem::take(item));
+    }
+
+    if !table.is_empty() {
+        table.set_implicit(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+    struct Database {
+        host: String,
+        port: u16
+    }
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+    struct Settings {
+        name: String,
+        database: Database
+    }
+
+    fn settings() -> Settings {
+        Settings {
+            name: String::from("Hello, world!"),
+            database: Database {
+                host: String::from("localhost"),
+                port: 5432
+            }
+        }
+    }
+
+    const DOCUMENT: &str = "# Greeting shown on startup
+name = 'Hello, world!'
+
+[database]
+port   = 5432 # Default postgres port
+host   = \"localhost\"
+";
+
+    #[test]
+    fn deserialize_bytes_to_struct() {
+        let mut f: TomlFormat<Settings> = TomlFormat::new();
+        assert_eq!(f.deserialize(DOCUMENT.as_bytes().to_vec(), None).unwrap().0, settings());
+    }
+
+    #[test]
+    fn deserialize_defaults() {
+        let mut f: TomlFormat<Settings> = TomlFormat::new();
+        let d: Deserialized<Settings> = f.deserialize(vec![], Some(&settings())).unwrap();
+        assert_eq!(d.0, settings());
+        assert_eq!(d.1, Defaulted::All);
+    }
+
+    #[test]
+    fn deserialize_merge_defaults() {
+        let mut f: TomlFormat<Settings> = TomlFormat::new().merge_defaults(true);
+        let d: Deserialized<Settings> = f.deserialize(b"name = 'Hi'\n\n[database]\nport = 5433\n".to_vec(), Some(&settings())).unwrap();
+
+        assert_eq!(d.0.name, "Hi");
+        assert_eq!(d.0.database, Database { port: 5433, ..settings().database });
+        assert_eq!(d.1, Defaulted::Keys(vec![ String::from("database.host") ]));
+
+        let s: String = String::from_utf8(f.serialize(Some(&d.0)).unwrap()).unwrap();
+        assert_eq!(s, "name = 'Hi'\n\n[database]\nport = 5433\nhost = \"localhost\"\n");
+    }
+
+    #[test]
+    fn value_round_trip() {
+        let mut f: TomlFormat<Settings> = TomlFormat::new();
+        let v: ConfigValue = f.parse(b"[database]\nport = 5433\n".to_vec()).unwrap();
+        assert_eq!(v.get("database.port"), Some(&ConfigValue::Integer(5433)));
+
+        let mut v: ConfigValue = f.to_value(&settings()).unwrap();
+        v.set("database.port", ConfigValue::Integer(5433));
+        assert_eq!(f.to_content(v).unwrap().database.port, 5433);
+    }
+
+    #[test]
+    fn deserialize_invalid_toml() {
+        let mut f: TomlFormat<Settings> = TomlFormat::new();
+        assert!(f.deserialize(b"name = ".to_vec(), None).is_err());
+    }
+
+    #[test]
+    fn serialize_new_document() {
+        let mut f: TomlFormat<Settings> = TomlFormat::new();
+        let s: String = String::from_utf8(f.serialize(Some(&settings())).unwrap()).unwrap();
+        assert_eq!(s, "name = \"Hello, world!\"\n\n[database]\nhost = \"localhost\"\nport = 5432\n");
+    }
+
+    #[test]
+    fn serialize_unchanged_preserves_document() {
+        let mut f: TomlFormat<Settings> = TomlFormat::new();
+        let c: Settings = f.deserialize(DOCUMENT.as_bytes().to_vec(), None).unwrap().0;
+        assert_eq!(f.serialize(Some(&c)).unwrap(), DOCUMENT.as_bytes().to_vec());
+    }
+
+    #[test]
+    fn serialize_changed_preserves_comments() {
+        let mut f: TomlFormat<Settings> = TomlFormat::new();
+        let mut c: Settings = f.deserialize(DOCUMENT.as_bytes().to_vec(), None).unwrap().0;
+
+        c.database.port = 5433;
+
+        let s: String = String::from_utf8(f.serialize(Some(&c)).unwrap()).unwrap();
+        assert_eq!(s, DOCUMENT.replace("5432", "5433"));
+    }
+
+}
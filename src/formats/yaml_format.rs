@@ -0,0 +1,310 @@
+use std::marker::PhantomData;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::format::{ Format, StructuredFormat, Deserialized, Defaulted };
+use crate::formats::merge;
+use crate::formats::empty;
+use crate::value::{ Value, Table };
+
+/// Format for YAML files holding a single document, (de)serializing content with serde
+pub struct YamlFormat<T> {
+    merge_defaults: bool,
+    content: PhantomData<fn() -> T>
+}
+
+#[allow(clippy::new_without_default)]
+impl<T> YamlFormat<T> {
+
+    pub fn new() -> Self {
+        Self {
+            merge_defaults: false,
+            content: PhantomData
+        }
+    }
+
+    /// Whether to fill in keys missing from the file with the defaults, instead of only using the defaults for empty files
+    pub fn merge_defaults(mut self, merge_defaults: bool) -> Self {
+        self.merge_defaults = merge_defaults;
+        self
+    }
+
+}
+
+impl<T> Clone for YamlFormat<T> {
+
+    fn clone(&self) -> Self {
+        Self::new().merge_defaults(self.merge_defaults)
+    }
+
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> Format for YamlFormat<T> {
+
+    type Content = T;
+    type Defaults = T;
+    type Error = serde_yaml::Error;
+
+    fn deserialize(&mut self, input: Vec<u8>, defaults: Option<&Self::Defaults>) -> Result<Deserialized<Self::Content>, Self::Error> {
+        if !input.is_empty() {
+            match defaults {
+                Some(__defaults) if self.merge_defaults => {
+                    let mut value: serde_yaml::Value = serde_yaml::from_slice(&input)?;
+                    let mut defaulted: Vec<String> = Vec::new();
+
+                    merge::merge_yaml(&mut value, serde_yaml::to_value(__defaults)?, "", &mut defaulted);
+
+                    Ok(Deserialized(serde_yaml::from_value(value)?, Defaulted::from_keys(defaulted)))
+                },
+                _ => Ok(Deserialized(serde_yaml::from_slice(&input)?, Defaulted::None))
+            }
+        } else {
+            Ok(Deserialized(match defaults {
+                Some(__defaults) => __defaults.clone(),
+                None => empty::empty::<T, Self::Error>()?
+            }, Defaulted::All))
+        }
+    }
+
+    fn serialize(&mut self, input: Option<&Self::Content>) -> Result<Vec<u8>, Self::Error> {
+        match input {
+            Some(__input) => Ok(serde_yaml::to_string(__input)?.into_bytes()),
+            None => Ok(Vec::new())
+        }
+    }
+
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> StructuredFormat for YamlFormat<T> {
+
+    fn parse(&mut self, input: Vec<u8>) -> Result<Value, Self::Error> {
+        if input.is_empty() {
+            return Ok(Value::default());
+        }
+
+        Ok(from_yaml(serde_yaml::from_slice(&input)?))
+    }
+
+    fn to_value(&mut self, content: &Self::Content) -> Result<Value, Self::Error> {
+        Ok(from_yaml(serde_yaml::to_value(content)?))
+    }
+
+    fn to_content(&mut self, value: Value) -> Result<Self::Content, Self::Error> {
+        serde_yaml::from_value(to_yaml(value))
+    }
+
+}
+
+fn from_yaml(value: serde_yaml::Value) -> Value {
+    match value {
+        serde_yaml::Value::Null => Value::Null,
+        serde_yaml::Value::Bool(value) => Value::Bool(value),
+        serde_yaml::Value::Number(number) => match number.as_i64() {
+            Some(value) => Value::Integer(value),
+            None => Value::Float(number.as_f64().unwrap_or(f64::NAN))
+        },
+        serde_yaml::Value::String(value) => Value::String(value),
+        serde_yaml::Value::Sequence(sequence) => Value::Array(sequence.into_iter().map(from_yaml).collect()),
+        serde_yaml::Value::Mapping(mapping) => Value::Table(mapping.into_iter()
+            .map(|( key, value )| {
+                let key: String = match key {
+                    serde_yaml::Value::String(key) => key,
+                    key => serde_yaml::to_string(&key).unwrap_or_default().trim_end().to_string()
+                };
+
+                ( key, from_yaml(value) )
+            })
+            .collect::<Table>()),
+        serde_yaml::Value::Tagged(tagged) => from_yaml(tagged.value)
+    }
+}
+
+fn to_yaml(value: Value) -> serde_yaml::Value {
+    match value {
+        Value::Null => serde_yaml::Value::Null,
+        Value::Bool(value) => serde_yaml::Value::Bool(value),
+        Value::Integer(value) => serde_yaml::Value::from(value),
+        Value::Float(value) => serde_yaml::Value::from(value),
+        Value::String(value) => serde_yaml::Value::String(value),
+        Value::Array(array) => serde_yaml::Value::Sequence(array.into_iter().map(to_yaml).collect()),
+        Value::Table(table) => serde_yaml::Value::Mapping(table.into_iter()
+            .map(|( key, value )| ( serde_yaml::Value::String(key), to_yaml(value) ))
+            .collect())
+    }
+}
+
+/// Format for YAML files holding any number of `---` separated documents,
+/// each (de)serialized as `T` with serde
+pub struct YamlDocumentsFormat<T> {
+    content: PhantomData<fn() -> T>
+}
+
+#[allow(clippy::new_without_default)]
+impl<T> YamlDocumentsFormat<T> {
+
+    pub fn new() -> Self {
+        Self {
+            content: PhantomData
+        }
+    }
+
+}
+
+impl<T> Clone for YamlDocumentsFormat<T> {
+
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> Format for YamlDocumentsFormat<T> {
+
+    type Content = Vec<T>;
+    type Defaults = Vec<T>;
+    type Error = serde_yaml::Error;
+
+    fn deserialize(&mut self, input: Vec<u8>, defaults: Option<&Self::Defaults>) -> Result<Deserialized<Self::Content>, Self::Error> {
+        if !input.is_empty() {
+            let mut documents: Vec<T> = Vec::new();
+
+            for document in serde_yaml::Deserializer::from_slice(&input) {
+                documents.push(T::deserialize(document)?);
+            }
+
+            Ok(Deserialized(documents, Defaulted::None))
+        } else {
+            Ok(Deserialized(match defaults {
+                Some(__defaults) => __defaults.clone(),
+                None => Vec::new()
+            }, Defaulted::All))
+        }
+    }
+
+    fn serialize(&mut self, input: Option<&Self::Content>) -> Result<Vec<u8>, Self::Error> {
+        let mut output: String = String::new();
+
+        if let Some(__input) = input {
+            for ( i, document ) in __input.iter().enumerate() {
+                if i > 0 {
+                    output.push_str("---\n");
+                }
+
+                output.push_str(&serde_yaml::to_string(document)?);
+            }
+        }
+
+        Ok(output.into_bytes())
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+    struct Service {
+        name: String,
+        replicas: u32
+    }
+
+    fn service(name: &str, replicas: u32) -> Service {
+        Service {
+            name: String::from(name),
+            replicas
+        }
+    }
+
+    #[test]
+    fn deserialize_bytes_to_struct() {
+        let mut f: YamlFormat<Service> = YamlFormat::new();
+        let b: Vec<u8> = b"name: web\nreplicas: 3\n".to_vec();
+        assert_eq!(f.deserialize(b, None).unwrap().0, service("web", 3));
+    }
+
+    #[test]
+    fn deserialize_defaults() {
+        let mut f: YamlFormat<Service> = YamlFormat::new();
+        let d: Deserialized<Service> = f.deserialize(vec![], Some(&service("web", 3))).unwrap();
+        assert_eq!(d.0, service("web", 3));
+        assert_eq!(d.1, Defaulted::All);
+    }
+
+    #[test]
+    fn deserialize_empty_without_defaults() {
+        #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+        struct Scaling {
+            #[serde(default)]
+            replicas: u32,
+            max: Option<u32>
+        }
+
+        let mut f: YamlFormat<Scaling> = YamlFormat::new();
+        let d: Deserialized<Scaling> = f.deserialize(vec![], None).unwrap();
+        assert_eq!(d.0, Scaling { replicas: 0, max: None });
+        assert_eq!(d.1, Defaulted::All);
+
+        let mut f: YamlFormat<Option<Service>> = YamlFormat::new();
+        assert_eq!(f.deserialize(vec![], None).unwrap().0, None);
+    }
+
+    #[test]
+    fn deserialize_merge_defaults() {
+        let mut f: YamlFormat<Service> = YamlFormat::new().merge_defaults(true);
+        let d: Deserialized<Service> = f.deserialize(b"name: worker\n".to_vec(), Some(&service("web", 3))).unwrap();
+        assert_eq!(d.0, service("worker", 3));
+        assert_eq!(d.1, Defaulted::Keys(vec![ String::from("replicas") ]));
+    }
+
+    #[test]
+    fn value_round_trip() {
+        let mut f: YamlFormat<Service> = YamlFormat::new();
+        let v: Value = f.parse(b"replicas: 1\n".to_vec()).unwrap();
+        assert_eq!(v.get("replicas"), Some(&Value::Integer(1)));
+
+        let mut v: Value = f.to_value(&service("web", 3)).unwrap();
+        v.set("replicas", Value::Integer(1));
+        assert_eq!(f.to_content(v).unwrap(), service("web", 1));
+    }
+
+    #[test]
+    fn deserialize_invalid_yaml() {
+        let mut f: YamlFormat<Service> = YamlFormat::new();
+        assert!(f.deserialize(b"name: [web\n".to_vec(), None).is_err());
+    }
+
+    #[test]
+    fn serialize_struct_to_bytes() {
+        let mut f: YamlFormat<Service> = YamlFormat::new();
+        assert_eq!(f.serialize(Some(&service("web", 3))).unwrap(), b"name: web\nreplicas: 3\n".to_vec());
+    }
+
+    #[test]
+    fn deserialize_documents() {
+        let mut f: YamlDocumentsFormat<Service> = YamlDocumentsFormat::new();
+        let b: Vec<u8> = b"name: web\nreplicas: 3\n---\nname: worker\nreplicas: 1\n".to_vec();
+        assert_eq!(f.deserialize(b, None).unwrap().0, vec![service("web", 3), service("worker", 1)]);
+    }
+
+    #[test]
+    fn deserialize_documents_defaults() {
+        let mut f: YamlDocumentsFormat<Service> = YamlDocumentsFormat::new();
+        let d: Deserialized<Vec<Service>> = f.deserialize(vec![], None).unwrap();
+        assert!(d.0.is_empty());
+        assert_eq!(d.1, Defaulted::All);
+    }
+
+    #[test]
+    fn serialize_documents() {
+        let mut f: YamlDocumentsFormat<Service> = YamlDocumentsFormat::new();
+        let d: Vec<Service> = vec![service("web", 3), service("worker", 1)];
+        let b: Vec<u8> = f.serialize(Some(&d)).unwrap();
+        assert_eq!(b, b"name: web\nreplicas: 3\n---\nname: worker\nreplicas: 1\n".to_vec());
+        assert_eq!(f.deserialize(b, None).unwrap().0, d);
+    }
+
+}
@@ -0,0 +1,361 @@
+use std::path::Path;
+use std::collections::BTreeMap;
+
+use crate::format::StructuredFormat;
+use crate::config_file::ConfigFile;
+use crate::error::{ ConfigError, Operation };
+use crate::value::Value;
+use crate::env::{ EnvOverrides, Guessed };
+use crate::args::ArgOverrides;
+use crate::profile::{ Profiles, BASE };
+
+enum Source<Format: StructuredFormat + Sized + Clone> {
+    Content(Format::Content),
+    Value(Value),
+    File(Box<ConfigFile<Format>>),
+    Env(EnvOverrides),
+    Args(ArgOverrides),
+    Profiles(Profiles<Format>)
+}
+
+/// Layer of a `Layered` config
+struct Layer<Format: StructuredFormat + Sized + Clone> {
+    name: String,
+    source: Source<Format>
+}
+
+/// Config stacked from layers of the same content, like
+/// built-in defaults < system file < user file < project file < environment variables < command line.
+///
+/// Layers are merged key by key in the order they're added, later layers taking precedence,
+/// so each layer only has to hold the keys it sets
+pub struct Layered<Format: StructuredFormat + Sized + Clone> {
+    format: Format,
+    layers: Vec<Layer<Format>>
+}
+
+impl<Format: StructuredFormat + Sized + Clone> Layered<Format> {
+
+    pub fn new(format: Format) -> Self {
+        Self {
+            format,
+            layers: Vec::new()
+        }
+    }
+
+    fn layer(mut self, name: &str, source: Source<Format>) -> Self {
+        self.layers.push(Layer {
+            name: String::from(name),
+            source
+        });
+        self
+    }
+
+    /// Adds `defaults` as a layer named `defaults`, usually the first
+    pub fn defaults(self, defaults: Format::Content) -> Self {
+        self.content("defaults", defaults)
+    }
+
+    /// Adds complete content as a layer
+    pub fn content(self, name: &str, content: Format::Content) -> Self {
+        self.layer(name, Source::Content(content))
+    }
+
+    /// Adds a tree of values as a layer, which may hold only some of the keys
+    pub fn value(self, name: &str, value: Value) -> Self {
+        self.layer(name, Source::Value(value))
+    }
+
+    /// Adds a file as a layer, which may hold only some of the keys. Files that don't exist are skipped
+    pub fn file(self, name: &str, config_file: ConfigFile<Format>) -> Self {
+        self.layer(name, Source::File(Box::new(config_file)))
+    }
+
+    /// Adds environment variables as a layer, overriding values of the layers before it, see `EnvOverrides`
+    pub fn env(self, name: &str, env: EnvOverrides) -> Self {
+        self.layer(name, Source::Env(env))
+    }
+
+    /// Adds command line arguments as a layer, overriding values of the layers before it, see `ArgOverrides`.
+    ///
+    /// A file given with `--config` replaces the path of the last file layer before it,
+    /// or is added as a layer named `config` if there is none
+    pub fn args(mut self, name: &str, args: ArgOverrides) -> Self {
+        if let Some(config) = args.config() {
+            let file: Option<&mut Box<ConfigFile<Format>>> = self.layers.iter_mut().rev().find_map(|layer| match &mut layer.source {
+                Source::File(config_file) => Some(config_file),
+                _ => None
+            });
+
+            match file {
+                Some(config_file) => {
+                    config_file.path = config.into();
+                    config_file.fallbacks.clear();
+                },
+                None => {
+                    let config_file: ConfigFile<Format> = ConfigFile::new(config, self.format.clone());
+
+                    self = self.file("config", config_file);
+                }
+            }
+        }
+
+        self.layer(name, Source::Args(args))
+    }
+
+    /// Adds the base and the active profile of `profiles` as layers, named `base` and after the profile
+    pub fn profiles(self, profiles: Profiles<Format>) -> Self {
+        self.layer(BASE, Source::Profiles(profiles))
+    }
+
+    /// Reads every layer and merges them.
+    ///
+    /// Errors of a layer are reported with it's name. Errors turning the merged layers into content
+    /// are reported for the path of the last file layer that exists, without a layer
+    pub fn read(mut self) -> Result<LayeredConfig<Format>, ConfigError> {
+        let mut value: Value = Value::default();
+        let mut sources: BTreeMap<String, String> = BTreeMap::new();
+        let mut path: Box<Path> = Path::new("").into();
+        let mut guessed: Guessed = Guessed::default();
+
+        for Layer { name, source } in self.layers {
+            let layer_value: Value = match source {
+                Source::Content(content) => self.format.to_value(&content)
+                    .map_err(|err| ConfigError::of_layer(&name, Operation::Serialize, err))?,
+                Source::Value(layer_value) => layer_value,
+                Source::File(mut config_file) => match config_file.read_value().map_err(|err| err.in_layer(&name))? {
+                    Some(layer_value) => {
+                        path = config_file.path.clone();
+                        layer_value
+                    },
+                    None => continue
+                },
+                Source::Env(env) => {
+                    // Applied in place, variables are converted to the types of the values merged so far
+                    for key_path in env.apply_guessing(&mut value, &mut guessed).map_err(|err| ConfigError::of_layer(&name, Operation::Deserialize, err))? {
+                        sources.insert(key_path, name.clone());
+                    }
+
+                    continue;
+                },
+                Source::Profiles(profiles) => {
+                    for ( profile_name, profile_path, profile_value ) in profiles.read_layers(&name)? {
+                        path = profile_path;
+                        value.merge_with(profile_value, &mut |key_path| {
+                            guessed.forget(key_path);
+                            sources.insert(String::from(key_path), profile_name.clone());
+                        });
+                    }
+
+                    continue;
+                },
+                Source::Args(args) => {
+                    for key_path in args.apply_guessing(&mut value, &mut guessed).map_err(|err| ConfigError::of_layer(&name, Operation::Deserialize, err))? {
+                        sources.insert(key_path, name.clone());
+                    }
+
+                    continue;
+                }
+            };
+
+            value.merge_with(layer_value, &mut |key_path| {
+                guessed.forget(key_path);
+                sources.insert(String::from(key_path), name.clone());
+            });
+        }
+
+        // Values replaced by a table of a later layer no longer exist
+        sources.retain(|key_path, _| value.get(key_path).is_some());
+
+        let content: Format::Content = guessed.to_content(&mut self.format, value.clone())
+            .map_err(|err| ConfigError::deserialize(&path, err))?;
+
+        Ok(LayeredConfig {
+            content,
+            value,
+            sources
+        })
+    }
+
+}
+
+/// Content merged from the layers of a `Layered` config, along with the layer each value came from
+pub struct LayeredConfig<Format: StructuredFormat> {
+    pub content: Format::Content,
+    /// The merged layers, as a tree of values
+    pub value: Value,
+
+    sources: BTreeMap<String, String>
+}
+
+impl<Format: StructuredFormat> LayeredConfig<Format> {
+
+    /// Name of the layer the value at `path`, like `database.port`, came from.
+    ///
+    /// Only values that aren't tables have a source, since the keys of a table may come from different layers
+    pub fn source(&self, path: &str) -> Option<&str> {
+        self.sources.get(path).map(String::as_str)
+    }
+
+    /// Paths of every value that isn't a table, along with the name of the layer it came from
+    pub fn sources(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.sources.iter().map(|( path, layer )| ( path.as_str(), layer.as_str() ))
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::fs;
+    use crate::test::test_path::TestPath;
+    use crate::test::child_path::ChildPath;
+    use crate::formats::env_file_format::{ EnvFileFormat, EnvFile };
+
+    fn env_file(entries: &[(&str, &str)]) -> EnvFile {
+        let mut e: EnvFile = EnvFile::new();
+
+        for ( key, value ) in entries {
+            e.set(key, value);
+        }
+
+        e
+    }
+
+    #[test]
+    fn precedence_and_sources() {
+        let tp: TestPath = TestPath::new();
+        let system: &Path = &tp.child_path("system.env");
+        let user: &Path = &tp.child_path("user.env");
+        let project: &Path = &tp.child_path("project.env");
+
+        fs::create_dir_all(&tp.path).unwrap();
+        fs::write(system, "HOST=db.internal\nPORT=5432\n").unwrap();
+        fs::write(project, "PORT=5433\n").unwrap();
+
+        std::env::set_var("LAYERED_ENV__HOST", "db.example.com");
+        std::env::set_var("LAYERED_ENV__DEBUG", "2");
+
+        let c: LayeredConfig<EnvFileFormat> = Layered::new(EnvFileFormat::new())
+            .defaults(env_file(&[ ( "HOST", "localhost" ), ( "PORT", "80" ), ( "DEBUG", "0" ) ]))
+            .file("system", ConfigFile::new(system, EnvFileFormat::new()))
+            .file("user", ConfigFile::new(user, EnvFileFormat::new()))
+            .file("project", ConfigFile::new(project, EnvFileFormat::new()))
+            .env("env", EnvOverrides::new("LAYERED_ENV"))
+            .args("cli", ArgOverrides::new().set("DEBUG", "1"))
+            .read()
+            .unwrap();
+
+        assert_eq!(c.content.get("HOST"), Some("db.example.com"));
+        assert_eq!(c.content.get("PORT"), Some("5433"));
+        assert_eq!(c.content.get("DEBUG"), Some("1"));
+        assert_eq!(c.sources().collect::<Vec<(&str, &str)>>(), vec![
+            ( "DEBUG", "cli" ),
+            ( "HOST", "env" ),
+            ( "PORT", "project" )
+        ]);
+    }
+
+    #[test]
+    fn args_config() {
+        let tp: TestPath = TestPath::new();
+        let user: &Path = &tp.child_path("user.env");
+        let other: &Path = &tp.child_path("other.env");
+
+        fs::create_dir_all(&tp.path).unwrap();
+        fs::write(user, "HOST=localhost\n").unwrap();
+        fs::write(other, "HOST=db.internal\n").unwrap();
+
+        let a = || ArgOverrides::parse(vec![ "--config", &other.display().to_string() ]).unwrap();
+        let c: LayeredConfig<EnvFileFormat> = Layered::new(EnvFileFormat::new())
+            .file("user", ConfigFile::new(user, EnvFileFormat::new()))
+            .args("cli", a())
+            .read()
+            .unwrap();
+
+        assert_eq!(c.content.get("HOST"), Some("db.internal"));
+        assert_eq!(c.source("HOST"), Some("user"));
+
+        let c: LayeredConfig<EnvFileFormat> = Layered::new(EnvFileFormat::new())
+            .args("cli", a())
+            .read()
+            .unwrap();
+
+        assert_eq!(c.source("HOST"), Some("config"));
+    }
+
+    #[test]
+    fn file_error() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.child_path("test.env");
+
+        fs::create_dir_all(&tp.path).unwrap();
+        fs::write(p, "A B\n").unwrap();
+
+        let err: ConfigError = Layered::new(EnvFileFormat::new())
+            .file("project", ConfigFile::new(p, EnvFileFormat::new()))
+            .read()
+            .err()
+            .unwrap();
+
+        assert_eq!(err.path(), p);
+        assert_eq!(err.layer(), Some("project"));
+
+        std::env::set_var("LAYERED_ERROR__A", "x");
+
+        let err: ConfigError = Layered::new(EnvFileFormat::new())
+            .value("defaults", Value::Table(vec![ ( String::from("a"), Value::from(1) ) ].into_iter().collect()))
+            .env("env", EnvOverrides::new("LAYERED_ERROR"))
+            .read()
+            .err()
+            .unwrap();
+
+        assert_eq!(err.path(), Path::new(""));
+        assert_eq!(err.layer(), Some("env"));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn nested_sources() {
+        use crate::formats::json_format::JsonFormat;
+        use serde::{ Serialize, Deserialize };
+
+        #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+        struct Database {
+            host: String,
+            port: u16
+        }
+
+        #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+        struct Settings {
+            name: String,
+            database: Database
+        }
+
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.child_path("settings.json");
+
+        fs::create_dir_all(&tp.path).unwrap();
+        fs::write(p, r#"{ "database": { "port": 5433 } }"#).unwrap();
+
+        let c: LayeredConfig<JsonFormat<Settings>> = Layered::new(JsonFormat::new())
+            .defaults(Settings {
+                name: String::from("Hello, world!"),
+                database: Database {
+                    host: String::from("localhost"),
+                    port: 5432
+                }
+            })
+            .file("project", ConfigFile::new(p, JsonFormat::new()))
+            .read()
+            .unwrap();
+
+        assert_eq!(c.content.database.port, 5433);
+        assert_eq!(c.content.database.host, "localhost");
+        assert_eq!(c.source("database.port"), Some("project"));
+        assert_eq!(c.source("database.host"), Some("defaults"));
+        assert_eq!(c.source("database"), None);
+    }
+
+}
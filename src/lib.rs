@@ -0,0 +1,25 @@
+#[cfg(test)]
+#[macro_use]
+extern crate lazy_static;
+
+pub mod error;
+pub mod format;
+pub mod value;
+pub mod formats;
+pub mod config_file;
+pub mod config_directory;
+pub mod backup;
+pub mod lock;
+#[cfg(feature = "watch")]
+pub mod watch;
+pub mod config;
+pub mod layered;
+pub mod env;
+pub mod args;
+pub mod xdg;
+pub mod discover;
+pub mod profile;
+pub mod interpolate;
+
+#[cfg(test)]
+pub mod test;
@@ -0,0 +1,157 @@
+use std::path::{ Path, PathBuf };
+use std::time::{ Duration, Instant };
+use std::ffi::OsString;
+use std::fs::{ self, File, TryLockError };
+use std::thread;
+use std::io;
+
+/// How to take the advisory lock of a file, shared while reading and exclusive while writing.
+///
+/// Locks are taken on a `.name.lock` file next to the config, since atomic writes replace the config itself.
+/// They're advisory, so they only keep out other processes locking the same file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Locking {
+    /// Don't lock
+    #[default]
+    None,
+    /// Wait for as long as the lock is held by someone else
+    Blocking,
+    /// Fail with `io::ErrorKind::WouldBlock` if the lock is held by someone else
+    Try,
+    /// Wait up to the duration, then fail with `io::ErrorKind::TimedOut`
+    Timeout(Duration)
+}
+
+/// Held lock, released when dropped
+pub(crate) struct Lock {
+    file: File
+}
+
+impl Drop for Lock {
+
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+
+}
+
+pub(crate) fn lock_path(path: &Path) -> PathBuf {
+    let mut name: OsString = OsString::from(".");
+
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".lock");
+
+    path.with_file_name(name)
+}
+
+fn try_lock(file: &File, exclusive: bool) -> Result<(), TryLockError> {
+    if exclusive {
+        file.try_lock()
+    } else {
+        file.try_lock_shared()
+    }
+}
+
+/// Locks the file at `path`, exclusively or shared, waiting as `locking` says
+pub(crate) fn lock(path: &Path, locking: Locking, exclusive: bool) -> io::Result<Option<Lock>> {
+    if locking == Locking::None {
+        return Ok(None);
+    }
+
+    let file: File = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock_path(path))?;
+
+    match locking {
+        Locking::None => (),
+        Locking::Blocking => if exclusive {
+            file.lock()?
+        } else {
+            file.lock_shared()?
+        },
+        Locking::Try => try_lock(&file, exclusive).map_err(io::Error::from)?,
+        Locking::Timeout(timeout) => {
+            let deadline: Instant = Instant::now() + timeout;
+
+            loop {
+                match try_lock(&file, exclusive) {
+                    Ok(()) => break,
+                    Err(TryLockError::WouldBlock) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+                    Err(TryLockError::WouldBlock) => return Err(io::Error::new(io::ErrorKind::TimedOut, "timed out waiting for lock")),
+                    Err(TryLockError::Error(err)) => return Err(err)
+                }
+            }
+        }
+    }
+
+    Ok(Some(Lock { file }))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test::test_path::TestPath;
+    use crate::test::child_path::ChildPath;
+
+    fn test_path(tp: &TestPath) -> Box<Path> {
+        fs::create_dir_all(&tp.path).unwrap();
+        tp.child_path("test.txt")
+    }
+
+    #[test]
+    fn shared_locks() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &test_path(&tp);
+        let _l: Option<Lock> = lock(p, Locking::Try, false).unwrap();
+
+        assert!(lock(p, Locking::Try, false).unwrap().is_some());
+    }
+
+    #[test]
+    fn try_exclusive() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &test_path(&tp);
+        let l: Option<Lock> = lock(p, Locking::Try, false).unwrap();
+
+        assert_eq!(lock(p, Locking::Try, true).err().unwrap().kind(), io::ErrorKind::WouldBlock);
+
+        drop(l);
+
+        assert!(lock(p, Locking::Try, true).unwrap().is_some());
+    }
+
+    #[test]
+    fn timeout() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &test_path(&tp);
+        let _l: Option<Lock> = lock(p, Locking::Blocking, true).unwrap();
+        let err: io::Error = lock(p, Locking::Timeout(Duration::from_millis(50)), false).err().unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn blocking_waits() {
+        let tp: TestPath = TestPath::new();
+        let p: Box<Path> = test_path(&tp);
+        let l: Option<Lock> = lock(&p, Locking::Blocking, true).unwrap();
+        let released: Instant = Instant::now() + Duration::from_millis(50);
+        let waiter = {
+            let p: Box<Path> = p.clone();
+            thread::spawn(move || {
+                lock(&p, Locking::Blocking, true).unwrap();
+                Instant::now()
+            })
+        };
+
+        thread::sleep(Duration::from_millis(50));
+        drop(l);
+
+        assert!(waiter.join().unwrap() >= released);
+    }
+
+}
@@ -0,0 +1,271 @@
+use std::path::Path;
+use std::error::Error;
+use std::fmt;
+use std::env;
+
+use crate::format::StructuredFormat;
+use crate::config_file::ConfigFile;
+use crate::layered::{ Layered, LayeredConfig };
+use crate::args::ArgOverrides;
+use crate::error::{ ConfigError, Operation };
+use crate::value::Value;
+
+/// Key of the table holding the profiles of a single file
+const PROFILES_KEY: &str = "profiles";
+
+/// Name of the base layer, and of the file holding it in a directory of profiles
+pub(crate) const BASE: &str = "base";
+
+enum Source {
+    /// File with the base at the top level and profiles in the `profiles` table
+    File(Box<Path>),
+    /// Directory with the base in `base.{extension}` and each profile in `{profile}.{extension}`
+    Directory(Box<Path>, String)
+}
+
+/// Config with named profiles, like `dev` and `prod`, whose values are overlaid over a base shared by every profile.
+///
+/// The active profile is picked by `profile`, `profile_env` and `profile_args` in the order they're called,
+/// each one replacing the profile picked before it if it has one. Without an active profile only the base is read
+pub struct Profiles<Format: StructuredFormat + Sized + Clone> {
+    format: Format,
+    source: Source,
+    active: Option<String>
+}
+
+impl<Format: StructuredFormat + Sized + Clone> Profiles<Format> {
+
+    /// Profiles within one file, the base at the top level and each profile in a table under `profiles`:
+    ///
+    /// ```toml
+    /// port = 80
+    ///
+    /// [profiles.dev]
+    /// port = 8080
+    /// ```
+    pub fn file(path: &Path, format: Format) -> Self {
+        Self {
+            format,
+            source: Source::File(path.into()),
+            active: None
+        }
+    }
+
+    /// Profiles in a directory, the base in `base.{extension}` and each profile in `{profile}.{extension}`.
+    ///
+    /// Unlike the children of a `ConfigDirectory`, each file is parsed into values rather than deserialized as the content,
+    /// since a profile usually holds only some of the keys. So the files are read with the default `ConfigFileOpts`
+    pub fn directory(path: &Path, extension: &str, format: Format) -> Self {
+        Self {
+            format,
+            source: Source::Directory(path.into(), String::from(extension)),
+            active: None
+        }
+    }
+
+    /// Picks `profile` as the active profile
+    pub fn profile(mut self, profile: &str) -> Self {
+        self.active = Some(String::from(profile));
+        self
+    }
+
+    /// Picks the profile named by the environment variable `var`, if it's set
+    pub fn profile_env(mut self, var: &str) -> Self {
+        if let Some(profile) = env::var(var).ok().filter(|profile| !profile.is_empty()) {
+            self.active = Some(profile);
+        }
+
+        self
+    }
+
+    /// Picks the profile given with `--profile`, if there is one
+    pub fn profile_args(mut self, args: &ArgOverrides) -> Self {
+        if let Some(profile) = args.profile() {
+            self.active = Some(String::from(profile));
+        }
+
+        self
+    }
+
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    /// Reads the base and the active profile, see `Layered::profiles` to layer them with other configs
+    pub fn read(self) -> Result<LayeredConfig<Format>, ConfigError> {
+        Layered::new(self.format.clone()).profiles(self).read()
+    }
+
+    /// Reads the base and the active profile as layers, the base named `base_name` and the profile after it,
+    /// along with the paths they were read from.
+    ///
+    /// A base that doesn't exist is empty, an active profile that doesn't exist or can't be the name of one is an error
+    pub(crate) fn read_layers(self, base_name: &str) -> Result<Vec<( String, Box<Path>, Value )>, ConfigError> {
+        if let Some(active) = &self.active {
+            if !is_profile_name(active) {
+                return Err(ConfigError::of_layer(active, Operation::Read, ProfileError::InvalidName(active.clone())));
+            }
+        }
+
+        match self.source {
+            Source::File(path) => {
+                let mut base: Value = ConfigFile::new(&path, self.format).read_value()
+                    .map_err(|err| err.in_layer(base_name))?
+                    .unwrap_or_default();
+                let profiles: Option<Value> = base.as_table_mut().and_then(|table| table.remove(PROFILES_KEY));
+                let mut layers: Vec<( String, Box<Path>, Value )> = vec![ ( String::from(base_name), path.clone(), base ) ];
+
+                if let Some(active) = self.active {
+                    let profile: Value = match profiles.as_ref().and_then(|__profiles| __profiles.get(&active)) {
+                        Some(profile) => profile.clone(),
+                        None => return Err(ConfigError::new(&path, Operation::Read, ProfileError::NotFound(active.clone())).in_layer(&active))
+                    };
+
+                    layers.push(( active, path, profile ));
+                }
+
+                Ok(layers)
+            },
+            Source::Directory(dir, extension) => {
+                let file_path = |name: &str| -> Box<Path> {
+                    dir.join(format!("{}.{}", name, extension)).into_boxed_path()
+                };
+                let base_path: Box<Path> = file_path(BASE);
+                let base: Value = ConfigFile::new(&base_path, self.format.clone()).read_value()
+                    .map_err(|err| err.in_layer(base_name))?
+                    .unwrap_or_default();
+                let mut layers: Vec<( String, Box<Path>, Value )> = vec![ ( String::from(base_name), base_path, base ) ];
+
+                if let Some(active) = self.active {
+                    let profile_path: Box<Path> = file_path(&active);
+                    let profile: Value = match ConfigFile::new(&profile_path, self.format).read_value().map_err(|err| err.in_layer(&active))? {
+                        Some(profile) => profile,
+                        None => return Err(ConfigError::new(&profile_path, Operation::Read, ProfileError::NotFound(active.clone())).in_layer(&active))
+                    };
+
+                    layers.push(( active, profile_path, profile ));
+                }
+
+                Ok(layers)
+            }
+        }
+    }
+
+}
+
+/// Whether `name` can name a profile, so it's not the base and can't name a file outside of a directory of profiles
+fn is_profile_name(name: &str) -> bool {
+    !name.is_empty() && name != BASE && !name.contains("..") && !name.contains(|c: char| c == '\\' || std::path::is_separator(c))
+}
+
+/// Error returned when the active profile can't be read
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProfileError {
+    /// The active profile doesn't exist
+    NotFound(String),
+    /// The active profile is named like the base, or contains path separators or `..`
+    InvalidName(String)
+}
+
+impl fmt::Display for ProfileError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProfileError::NotFound(name) => write!(f, "profile {} not found", name),
+            ProfileError::InvalidName(name) => write!(f, "invalid profile name {}", name)
+        }
+    }
+
+}
+
+impl Error for ProfileError {}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::fs;
+    use crate::test::test_path::TestPath;
+    use crate::test::child_path::ChildPath;
+    use crate::formats::env_file_format::EnvFileFormat;
+
+    #[test]
+    fn directory_profiles() {
+        let tp: TestPath = TestPath::new();
+
+        fs::create_dir_all(&tp.path).unwrap();
+        fs::write(tp.child_path("base.env"), "HOST=localhost\nPORT=80\n").unwrap();
+        fs::write(tp.child_path("prod.env"), "HOST=db.internal\n").unwrap();
+        std::env::set_var("PROFILE_DIRECTORY", "prod");
+
+        let p = || Profiles::directory(&tp.path, "env", EnvFileFormat::new());
+        let c: LayeredConfig<EnvFileFormat> = p()
+            .profile("dev")
+            .profile_env("PROFILE_DIRECTORY")
+            .profile_args(&ArgOverrides::new())
+            .read()
+            .unwrap();
+
+        assert_eq!(c.content.get("HOST"), Some("db.internal"));
+        assert_eq!(c.content.get("PORT"), Some("80"));
+        assert_eq!(c.source("HOST"), Some("prod"));
+        assert_eq!(p().read().unwrap().content.get("HOST"), Some("localhost"));
+
+        let err: ConfigError = p()
+            .profile_args(&ArgOverrides::parse(vec![ "--profile", "dev" ]).unwrap())
+            .read()
+            .err()
+            .unwrap();
+
+        assert_eq!(err.path(), &*tp.child_path("dev.env"));
+        assert_eq!(err.layer(), Some("dev"));
+        assert_eq!(err.to_string(), format!("failed to read {} in layer dev: profile dev not found", tp.child_path("dev.env").display()));
+    }
+
+    #[test]
+    fn invalid_profile_names() {
+        let tp: TestPath = TestPath::new();
+
+        fs::create_dir_all(tp.child_path("profiles")).unwrap();
+        fs::write(tp.child_path("profiles/base.env"), "HOST=localhost\n").unwrap();
+        fs::write(tp.child_path("secret.env"), "HOST=secret\n").unwrap();
+
+        for name in [ "../secret", "base", "a/b", "" ] {
+            let err: ConfigError = Profiles::directory(&tp.child_path("profiles"), "env", EnvFileFormat::new())
+                .profile(name)
+                .read()
+                .err()
+                .unwrap();
+
+            assert_eq!(err.to_string(), format!("failed to read in layer {}: invalid profile name {}", name, name));
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn file_profiles() {
+        use crate::formats::json_format::JsonFormat;
+        use serde::{ Serialize, Deserialize };
+
+        #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+        struct Settings {
+            host: String,
+            port: u16
+        }
+
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.child_path("settings.json");
+
+        fs::create_dir_all(&tp.path).unwrap();
+        fs::write(p, r#"{ "host": "localhost", "port": 80, "profiles": { "dev": { "port": 8080 } } }"#).unwrap();
+
+        let c: LayeredConfig<JsonFormat<Settings>> = Profiles::file(p, JsonFormat::new())
+            .profile("dev")
+            .read()
+            .unwrap();
+
+        assert_eq!(c.content, Settings { host: String::from("localhost"), port: 8080 });
+        assert_eq!(c.source("port"), Some("dev"));
+    }
+
+}
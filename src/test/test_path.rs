@@ -21,9 +21,9 @@ impl TestPath {
 
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        let id: &mut u32 = &mut *ID.lock().unwrap();
+        let id: &mut u32 = &mut ID.lock().unwrap();
 
-        if *id == u32::max_value() {
+        if *id == u32::MAX {
             panic!("Maximum amount of paths reached");
         }
 
@@ -32,7 +32,7 @@ impl TestPath {
         dir_path_buf.push(Path::new("."));
         dir_path_buf.push(Path::new("tmp"));
 
-        let dir: &mut HashSet<u32> = &mut *DIR.lock().unwrap(); // Set of ID:s currently using folder
+        let dir: &mut HashSet<u32> = &mut DIR.lock().unwrap(); // Set of ID:s currently using folder
         let dir_path: &Path = dir_path_buf.as_path();
         let mut path_buf: PathBuf = PathBuf::new();
 
@@ -71,7 +71,7 @@ impl ChildPath for TestPath {
 impl Drop for TestPath {
 
     fn drop(&mut self) {
-        let dir: &mut HashSet<u32> = &mut *DIR.lock().unwrap();
+        let dir: &mut HashSet<u32> = &mut DIR.lock().unwrap();
 
         dir.remove(&self.id);
 
@@ -0,0 +1,519 @@
+use std::fmt;
+use std::iter::FromIterator;
+use std::slice;
+use std::vec;
+
+use crate::formats::merge;
+
+/// Keys of a table and their values, in the order they were read or added
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Table {
+    entries: Vec<(String, Value)>
+}
+
+impl Table {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries.iter()
+            .find(|( __key, _ )| __key == key)
+            .map(|( _, value )| value)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.entries.iter_mut()
+            .find(|( __key, _ )| __key == key)
+            .map(|( _, value )| value)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Sets `key` to `value`, keeping it's position if it already exists, and returns the value it replaced
+    pub fn insert(&mut self, key: String, value: Value) -> Option<Value> {
+        match self.get_mut(&key) {
+            Some(__value) => Some(std::mem::replace(__value, value)),
+            None => {
+                self.entries.push(( key, value ));
+                None
+            }
+        }
+    }
+
+    /// Value of `key`, inserting null if it doesn't exist
+    pub fn entry(&mut self, key: &str) -> &mut Value {
+        let index: usize = match self.entries.iter().position(|( __key, _ )| __key == key) {
+            Some(index) => index,
+            None => {
+                self.entries.push(( String::from(key), Value::Null ));
+                self.entries.len() - 1
+            }
+        };
+
+        &mut self.entries[index].1
+    }
+
+    /// Removes `key`, keeping the order of the keys after it
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        let index: usize = self.entries.iter().position(|( __key, _ )| __key == key)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter().map(|( key, _ )| key)
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, (String, Value)> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+}
+
+impl IntoIterator for Table {
+
+    type Item = (String, Value);
+    type IntoIter = vec::IntoIter<(String, Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+
+}
+
+impl<'a> IntoIterator for &'a Table {
+
+    type Item = &'a (String, Value);
+    type IntoIter = slice::Iter<'a, (String, Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+
+}
+
+impl Extend<(String, Value)> for Table {
+
+    fn extend<I: IntoIterator<Item = (String, Value)>>(&mut self, iter: I) {
+        for ( key, value ) in iter {
+            self.insert(key, value);
+        }
+    }
+
+}
+
+impl FromIterator<(String, Value)> for Table {
+
+    fn from_iter<I: IntoIterator<Item = (String, Value)>>(iter: I) -> Self {
+        let mut table: Table = Table::new();
+        table.extend(iter);
+        table
+    }
+
+}
+
+/// Tree of config values, independent of the format they were read from.
+///
+/// Used to merge and override configs key by key, with keys addressed by paths like `database.port`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Value>),
+    Table(Table)
+}
+
+impl Default for Value {
+
+    fn default() -> Self {
+        Value::Table(Table::new())
+    }
+
+}
+
+impl Value {
+
+    /// Name of the type of the value, for error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Integer(_) => "integer",
+            Value::Float(_) => "float",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Table(_) => "table"
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(*value),
+            _ => None
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(value) => Some(*value),
+            _ => None
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Float(value) => Some(*value),
+            Value::Integer(value) => Some(*value as f64),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(array) => Some(array),
+            _ => None
+        }
+    }
+
+    pub fn as_table(&self) -> Option<&Table> {
+        match self {
+            Value::Table(table) => Some(table),
+            _ => None
+        }
+    }
+
+    pub fn as_table_mut(&mut self) -> Option<&mut Table> {
+        match self {
+            Value::Table(table) => Some(table),
+            _ => None
+        }
+    }
+
+    /// Value at a path of keys separated by dots, like `database.port`
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        let mut value: &Value = self;
+
+        for key in path.split('.') {
+            value = value.as_table()?.get(key)?;
+        }
+
+        Some(value)
+    }
+
+    pub fn get_mut(&mut self, path: &str) -> Option<&mut Value> {
+        let mut value: &mut Value = self;
+
+        for key in path.split('.') {
+            value = value.as_table_mut()?.get_mut(key)?;
+        }
+
+        Some(value)
+    }
+
+    /// Sets the value at a path of keys separated by dots,
+    /// creating missing tables and replacing values that aren't tables along the way
+    pub fn set(&mut self, path: &str, new: Value) {
+        let mut value: &mut Value = self;
+
+        for key in path.split('.') {
+            if value.as_table().is_none() {
+                *value = Value::Table(Table::new());
+            }
+
+            value = value.as_table_mut().unwrap().entry(key);
+        }
+
+        *value = new;
+    }
+
+    /// Removes the value at a path of keys separated by dots
+    pub fn remove(&mut self, path: &str) -> Option<Value> {
+        match path.rsplit_once('.') {
+            Some(( parent, key )) => self.get_mut(parent)?.as_table_mut()?.remove(key),
+            None => self.as_table_mut()?.remove(path)
+        }
+    }
+
+    /// Merges `other` into the value, recursing into tables present in both and replacing everything else.
+    ///
+    /// `merged` is called with the path of every value taken from `other`
+    pub fn merge_with<F: FnMut(&str)>(&mut self, other: Value, merged: &mut F) {
+        self.merge_at("", other, merged);
+    }
+
+    pub fn merge(&mut self, other: Value) {
+        self.merge_with(other, &mut |_| ());
+    }
+
+    fn merge_at<F: FnMut(&str)>(&mut self, path: &str, other: Value, merged: &mut F) {
+        match ( self, other ) {
+            ( Value::Table(table), Value::Table(other) ) => {
+                for ( key, value ) in other {
+                    let key_path: String = merge::key_path(path, &key);
+
+                    match table.get_mut(&key) {
+                        Some(existing) => existing.merge_at(&key_path, value, merged),
+                        None => {
+                            value.leaves(&key_path, merged);
+                            table.insert(key, value);
+                        }
+                    }
+                }
+            },
+            ( existing, other ) => {
+                other.leaves(path, merged);
+                *existing = other;
+            }
+        }
+    }
+
+    /// Flattens the tree into pairs of paths and values written as strings,
+    /// for formats holding flat lists of strings. Arrays are joined with commas
+    pub(crate) fn flatten(&self) -> Vec<(String, String)> {
+        let mut flat: Vec<(String, String)> = Vec::new();
+        self.flatten_at("", &mut flat);
+        flat
+    }
+
+    fn flatten_at(&self, path: &str, flat: &mut Vec<(String, String)>) {
+        match self {
+            Value::Table(table) => for ( key, value ) in table {
+                value.flatten_at(&merge::key_path(path, key), flat);
+            },
+            value => if let Some(__value) = value.to_flat_string() {
+                flat.push(( String::from(path), __value ));
+            }
+        }
+    }
+
+    /// The value written as a string, `None` for tables and null
+    pub(crate) fn to_flat_string(&self) -> Option<String> {
+        match self {
+            Value::Null | Value::Table(_) => None,
+            Value::String(value) => Some(value.clone()),
+            Value::Array(array) => Some(array.iter()
+                .filter_map(Value::to_flat_string)
+                .collect::<Vec<String>>()
+                .join(",")),
+            value => Some(value.to_string())
+        }
+    }
+
+    /// Paths of every value that isn't a table, tables being the branches of the tree
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = Vec::new();
+        self.leaves("", &mut |path| paths.push(String::from(path)));
+        paths
+    }
+
+    fn leaves<F: FnMut(&str)>(&self, path: &str, f: &mut F) {
+        match self {
+            Value::Table(table) if !table.is_empty() || path.is_empty() => for ( key, value ) in table {
+                value.leaves(&merge::key_path(path, key), f);
+            },
+            _ => f(path)
+        }
+    }
+
+}
+
+impl fmt::Display for Value {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(value) => value.fmt(f),
+            Value::Integer(value) => value.fmt(f),
+            Value::Float(value) => value.fmt(f),
+            Value::String(value) => value.fmt(f),
+            Value::Array(array) => {
+                f.write_str("[")?;
+
+                for ( i, value ) in array.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+
+                    value.fmt(f)?;
+                }
+
+                f.write_str("]")
+            },
+            Value::Table(table) => {
+                f.write_str("{")?;
+
+                for ( i, ( key, value ) ) in table.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+
+                    write!(f, " {} = {}", key, value)?;
+                }
+
+                f.write_str(" }")
+            }
+        }
+    }
+
+}
+
+impl From<bool> for Value {
+
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+
+}
+
+impl From<i64> for Value {
+
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+
+}
+
+impl From<f64> for Value {
+
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+
+}
+
+impl From<&str> for Value {
+
+    fn from(value: &str) -> Self {
+        Value::String(String::from(value))
+    }
+
+}
+
+impl From<String> for Value {
+
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+
+}
+
+#[cfg(any(feature = "json", feature = "json5", feature = "ron", feature = "msgpack", feature = "cbor"))]
+impl From<serde_json::Value> for Value {
+
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(value) => Value::Bool(value),
+            serde_json::Value::Number(number) => match number.as_i64() {
+                Some(value) => Value::Integer(value),
+                None => Value::Float(number.as_f64().unwrap_or(f64::NAN))
+            },
+            serde_json::Value::String(value) => Value::String(value),
+            serde_json::Value::Array(array) => Value::Array(array.into_iter().map(Value::from).collect()),
+            serde_json::Value::Object(object) => Value::Table(object.into_iter()
+                .map(|( key, value )| ( key, Value::from(value) ))
+                .collect())
+        }
+    }
+
+}
+
+#[cfg(any(feature = "json", feature = "json5", feature = "ron", feature = "msgpack", feature = "cbor"))]
+impl From<Value> for serde_json::Value {
+
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(value) => serde_json::Value::Bool(value),
+            Value::Integer(value) => serde_json::Value::from(value),
+            Value::Float(value) => serde_json::Value::from(value),
+            Value::String(value) => serde_json::Value::String(value),
+            Value::Array(array) => serde_json::Value::Array(array.into_iter().map(serde_json::Value::from).collect()),
+            Value::Table(table) => serde_json::Value::Object(table.into_iter()
+                .map(|( key, value )| ( key, serde_json::Value::from(value) ))
+                .collect())
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn table(entries: &[(&str, Value)]) -> Value {
+        Value::Table(entries.iter().map(|( key, value )| ( String::from(*key), value.clone() )).collect())
+    }
+
+    #[test]
+    fn get_set_paths() {
+        let mut v: Value = Value::default();
+
+        v.set("database.host", Value::from("localhost"));
+        v.set("database.port", Value::from(5432));
+
+        assert_eq!(v.get("database.port"), Some(&Value::Integer(5432)));
+        assert_eq!(v.get("database.user"), None);
+        assert_eq!(v.paths(), vec![ "database.host", "database.port" ]);
+        assert_eq!(v.remove("database.host"), Some(Value::from("localhost")));
+        assert_eq!(v.paths(), vec![ "database.port" ]);
+    }
+
+    #[test]
+    fn table_keeps_order() {
+        let mut t: Table = vec![
+            ( String::from("b"), Value::from(1) ),
+            ( String::from("a"), Value::from(2) ),
+            ( String::from("c"), Value::from(3) )
+        ].into_iter().collect();
+
+        assert_eq!(t.insert(String::from("b"), Value::from(4)), Some(Value::from(1)));
+        assert_eq!(t.remove("a"), Some(Value::from(2)));
+        *t.entry("a") = Value::from(5);
+
+        assert_eq!(t.keys().collect::<Vec<&String>>(), vec![ "b", "c", "a" ]);
+        assert_eq!(t.get("b"), Some(&Value::from(4)));
+    }
+
+    #[test]
+    fn merge_reports_paths() {
+        let mut v: Value = table(&[
+            ( "name", Value::from("a") ),
+            ( "database", table(&[ ( "host", Value::from("localhost") ) ]) )
+        ]);
+        let mut merged: Vec<String> = Vec::new();
+
+        v.merge_with(table(&[
+            ( "database", table(&[ ( "port", Value::from(5432) ) ]) ),
+            ( "tags", Value::Array(vec![ Value::from("b") ]) )
+        ]), &mut |path| merged.push(String::from(path)));
+
+        assert_eq!(v.get("name"), Some(&Value::from("a")));
+        assert_eq!(v.get("database.host"), Some(&Value::from("localhost")));
+        assert_eq!(v.get("database.port"), Some(&Value::from(5432)));
+        assert_eq!(merged, vec![ "database.port", "tags" ]);
+    }
+
+}
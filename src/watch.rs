@@ -0,0 +1,389 @@
+use std::path::{ Path, PathBuf };
+use std::fs;
+use std::sync::{ Arc, Weak, Mutex, mpsc };
+use std::thread::{ self, JoinHandle };
+use std::time::Duration;
+
+use notify::{ Event, EventKind, RecursiveMode, RecommendedWatcher, PollWatcher, Watcher as _ };
+
+use crate::format;
+use crate::config;
+use crate::config_file::ConfigFile;
+use crate::config_directory::ConfigDirectory;
+use crate::error::{ ConfigError, Operation };
+
+/// Config that can be watched for changes by `Watcher`
+pub trait Watch: Clone + Send + 'static {
+
+    /// Path to watch, along with whether to watch it's descendants
+    fn watch_path(&self) -> (PathBuf, RecursiveMode);
+
+    /// Whether a change to the file at `path` calls for reading the config again
+    fn is_change(&self, path: &Path) -> bool;
+
+    fn reread(self) -> Result<Self, ConfigError>;
+
+}
+
+impl<Format> Watch for ConfigFile<Format>
+where
+    Format: format::Format + Sized + Clone + Send + 'static,
+    Format::Content: Clone + Send,
+    Format::Defaults: Send
+{
+
+    /// Watches the parent directory rather than the file itself,
+    /// since atomic writes replace the file we'd otherwise be watching
+    fn watch_path(&self) -> (PathBuf, RecursiveMode) {
+        let parent: &Path = match self.path.parent() {
+            Some(parent) if parent != Path::new("") => parent,
+            _ => Path::new(".")
+        };
+
+        ( parent.to_path_buf(), RecursiveMode::NonRecursive )
+    }
+
+    fn is_change(&self, path: &Path) -> bool {
+        absolute(path) == absolute(&self.path)
+    }
+
+    fn reread(self) -> Result<Self, ConfigError> {
+        self.read()
+    }
+
+}
+
+impl<Format> Watch for ConfigDirectory<Format>
+where
+    Format: format::Format + Sized + Clone + Send + 'static,
+    Format::Content: Clone + Send,
+    Format::Defaults: Send
+{
+
+    fn watch_path(&self) -> (PathBuf, RecursiveMode) {
+        ( self.path.to_path_buf(), RecursiveMode::Recursive )
+    }
+
+    fn is_change(&self, path: &Path) -> bool {
+        if !absolute(path).starts_with(absolute(&self.path)) {
+            return false;
+        }
+
+        match path.file_name() {
+            Some(file_name) => {
+                let file_name: &str = &file_name.to_string_lossy();
+                !config::is_temporary(file_name) && !file_name.ends_with(".bak")
+            },
+            None => true
+        }
+    }
+
+    fn reread(self) -> Result<Self, ConfigError> {
+        self.read()
+    }
+
+}
+
+pub struct WatchOpts {
+    /// How long to wait for more changes after one, so a burst of changes is read once
+    pub debounce: Duration,
+    /// Poll for changes instead of using the notifications of the platform, like inotify on linux.
+    /// Polling is also used when those aren't available
+    pub poll: bool,
+    /// How often to poll for changes
+    pub poll_interval: Duration
+}
+
+impl Default for WatchOpts {
+
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_millis(100),
+            poll: false,
+            poll_interval: Duration::from_secs(1)
+        }
+    }
+
+}
+
+/// Reads a config again whenever it changes, until dropped.
+///
+/// Each read is delivered to a callback. When reading fails, the error is delivered
+/// and the last config read successfully is kept to read from on the next change
+pub struct Watcher<Config: Watch> {
+    latest: Arc<Mutex<Config>>,
+    watcher: Option<Arc<Mutex<Box<dyn notify::Watcher + Send>>>>,
+    thread: Option<JoinHandle<()>>
+}
+
+impl<Config: Watch> Watcher<Config> {
+
+    /// Watches `config`, which should have been read already, calling `callback` with every new read
+    pub fn new<Callback>(config: Config, options: WatchOpts, mut callback: Callback) -> Result<Self, ConfigError>
+    where
+        Callback: FnMut(Result<Config, ConfigError>) + Send + 'static
+    {
+        let ( path, recursive_mode ) = config.watch_path();
+        let ( sender, receiver ) = mpsc::channel::<notify::Result<Event>>();
+        // Until the path exists, the nearest ancestor that does is watched for the next part of the path
+        let mut watching: PathBuf = existing_ancestor(&path).to_path_buf();
+        let watching_mode: RecursiveMode = if watching == path { recursive_mode } else { RecursiveMode::NonRecursive };
+
+        let watcher: Box<dyn notify::Watcher + Send> = match Self::watcher(&watching, watching_mode, &options, sender.clone()) {
+            Ok(watcher) => watcher,
+            Err(_) if !options.poll => Self::watcher(&watching, watching_mode, &WatchOpts { poll: true, ..options }, sender)
+                .map_err(|err| ConfigError::new(&watching, Operation::Watch, err))?,
+            Err(err) => return Err(ConfigError::new(&watching, Operation::Watch, err))
+        };
+        let watcher: Arc<Mutex<Box<dyn notify::Watcher + Send>>> = Arc::new(Mutex::new(watcher));
+        // Weak, so dropping the `Watcher` still drops the notify watcher and ends the thread
+        let thread_watcher: Weak<Mutex<Box<dyn notify::Watcher + Send>>> = Arc::downgrade(&watcher);
+        let latest: Arc<Mutex<Config>> = Arc::new(Mutex::new(config));
+        let thread_latest: Arc<Mutex<Config>> = latest.clone();
+        let debounce: Duration = options.debounce;
+
+        let thread: JoinHandle<()> = thread::spawn(move || {
+            // Ends once the watcher, and with it the sender, is dropped
+            while let Ok(event) = receiver.recv() {
+                let mut changed: bool = false;
+                let mut event: notify::Result<Event> = event;
+
+                loop {
+                    match event {
+                        Ok(event) => {
+                            let config: &Config = &thread_latest.lock().unwrap();
+
+                            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) | EventKind::Any)
+                                && event.paths.iter().any(|path| config.is_change(path)) {
+                                changed = true;
+                            }
+                        },
+                        Err(err) => callback(Err(ConfigError::new(&path, Operation::Watch, err)))
+                    }
+
+                    if watching != path {
+                        match retarget(&thread_watcher, &mut watching, &path, recursive_mode) {
+                            // Changes made before the path was watched don't have events of their own
+                            Ok(true) => changed |= fs::read_dir(&path).into_iter().flatten().flatten()
+                                .any(|entry| thread_latest.lock().unwrap().is_change(&entry.path())),
+                            Ok(false) => (),
+                            Err(err) => callback(Err(ConfigError::new(&path, Operation::Watch, err)))
+                        }
+                    }
+
+                    // Only read once the changes have settled
+                    event = match receiver.recv_timeout(debounce) {
+                        Ok(event) => event,
+                        Err(_) => break
+                    };
+                }
+
+                if changed {
+                    let config: Config = thread_latest.lock().unwrap().clone();
+
+                    match config.reread() {
+                        Ok(config) => {
+                            *thread_latest.lock().unwrap() = config.clone();
+                            callback(Ok(config));
+                        },
+                        Err(err) => callback(Err(err))
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            latest,
+            watcher: Some(watcher),
+            thread: Some(thread)
+        })
+    }
+
+    /// Watches `config`, sending every new read to the returned receiver
+    pub fn channel(config: Config, options: WatchOpts) -> Result<(Self, mpsc::Receiver<Result<Config, ConfigError>>), ConfigError> {
+        let ( sender, receiver ) = mpsc::channel();
+        let watcher: Self = Self::new(config, options, move |read| {
+            let _ = sender.send(read);
+        })?;
+
+        Ok(( watcher, receiver ))
+    }
+
+    /// Last config read successfully
+    pub fn latest(&self) -> Config {
+        self.latest.lock().unwrap().clone()
+    }
+
+    fn watcher(path: &Path, recursive_mode: RecursiveMode, options: &WatchOpts, sender: mpsc::Sender<notify::Result<Event>>) -> notify::Result<Box<dyn notify::Watcher + Send>> {
+        let mut watcher: Box<dyn notify::Watcher + Send> = if options.poll {
+            Box::new(PollWatcher::new(sender, notify::Config::default()
+                .with_poll_interval(options.poll_interval)
+                .with_compare_contents(true))?)
+        } else {
+            Box::new(RecommendedWatcher::new(sender, notify::Config::default())?)
+        };
+
+        watcher.watch(path, recursive_mode)?;
+
+        Ok(watcher)
+    }
+
+}
+
+/// Nearest ancestor of `path` that exists, starting with `path` itself
+fn existing_ancestor(path: &Path) -> &Path {
+    match path.ancestors().find(|ancestor| ancestor.is_dir()) {
+        Some(ancestor) if ancestor != Path::new("") => ancestor,
+        _ => Path::new(".")
+    }
+}
+
+/// Moves the watch from `watching` down to the nearest existing ancestor of `target`, as the parts of it's path are created.
+///
+/// Returns whether `target` is watched now and wasn't before
+fn retarget(watcher: &Weak<Mutex<Box<dyn notify::Watcher + Send>>>, watching: &mut PathBuf, target: &Path, recursive_mode: RecursiveMode) -> notify::Result<bool> {
+    let watcher: Arc<Mutex<Box<dyn notify::Watcher + Send>>> = match watcher.upgrade() {
+        Some(watcher) => watcher,
+        None => return Ok(false)
+    };
+    let mut watcher = watcher.lock().unwrap();
+
+    // Parts created while moving the watch have no events, so look again until nothing new exists
+    while existing_ancestor(target) != watching.as_path() {
+        let next: PathBuf = existing_ancestor(target).to_path_buf();
+
+        watcher.watch(&next, if next == target { recursive_mode } else { RecursiveMode::NonRecursive })?;
+        let _ = watcher.unwatch(watching);
+        *watching = next;
+
+        if watching.as_path() == target {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// `path` made absolute, so the paths of events compare equal to the paths of configs however they're written
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl<Config: Watch> Drop for Watcher<Config> {
+
+    fn drop(&mut self) {
+        self.watcher.take();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::fs;
+    use crate::test::test_path::TestPath;
+    use crate::test::child_path::ChildPath;
+    use crate::formats::string_format::StringFormat;
+    use crate::config::Config;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    fn options(poll: bool) -> WatchOpts {
+        WatchOpts {
+            debounce: Duration::from_millis(50),
+            poll,
+            poll_interval: Duration::from_millis(50)
+        }
+    }
+
+    fn watch_file(poll: bool) {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.child_path("test.txt");
+
+        fs::create_dir_all(&tp.path).unwrap();
+        fs::write(p, "Hello, world!").unwrap();
+
+        let c: ConfigFile<StringFormat> = ConfigFile::new(p, StringFormat::new()).read().unwrap();
+        let ( w, r ) = Watcher::channel(c, options(poll)).unwrap();
+
+        fs::write(p, "Goodbye, world!").unwrap();
+        assert_eq!(r.recv_timeout(TIMEOUT).unwrap().unwrap().content.unwrap(), "Goodbye, world!");
+
+        fs::write(p, vec![0xff, 0xfe]).unwrap();
+        assert_eq!(r.recv_timeout(TIMEOUT).unwrap().err().unwrap().operation(), Operation::Deserialize);
+        assert_eq!(w.latest().content.unwrap(), "Goodbye, world!");
+    }
+
+    #[test]
+    fn watch_file_notify() {
+        watch_file(false);
+    }
+
+    #[test]
+    fn watch_file_poll() {
+        watch_file(true);
+    }
+
+    fn watch_file_in_missing_directory(poll: bool) {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.child_path("a/b/test.txt");
+
+        fs::create_dir_all(&tp.path).unwrap();
+
+        let c: ConfigFile<StringFormat> = ConfigFile::new(p, StringFormat::new()).read().unwrap();
+        let ( _w, r ) = Watcher::channel(c, options(poll)).unwrap();
+
+        assert!(!tp.child_path("a").exists());
+
+        // Same name, but not the watched file
+        fs::create_dir_all(tp.child_path("c")).unwrap();
+        fs::write(tp.child_path("c/test.txt"), "Goodbye, world!").unwrap();
+        fs::write(tp.child_path("test.txt"), "Goodbye, world!").unwrap();
+        assert!(r.recv_timeout(Duration::from_millis(500)).is_err());
+
+        fs::create_dir_all(tp.child_path("a/b")).unwrap();
+        fs::write(p, "Hello, world!").unwrap();
+        assert_eq!(r.recv_timeout(TIMEOUT).unwrap().unwrap().content.unwrap(), "Hello, world!");
+
+        fs::write(p, "Hello again, world!").unwrap();
+        assert_eq!(r.recv_timeout(TIMEOUT).unwrap().unwrap().content.unwrap(), "Hello again, world!");
+    }
+
+    #[test]
+    fn watch_file_in_missing_directory_notify() {
+        watch_file_in_missing_directory(false);
+    }
+
+    #[test]
+    fn watch_file_in_missing_directory_poll() {
+        watch_file_in_missing_directory(true);
+    }
+
+    #[test]
+    fn watch_directory() {
+        let tp: TestPath = TestPath::new();
+        let p: &Path = &tp.path;
+
+        fs::create_dir_all(p).unwrap();
+        fs::write(tp.child_path("test.txt"), "Hello, world!").unwrap();
+
+        let c: ConfigDirectory<StringFormat> = ConfigDirectory::new(p, StringFormat::new())
+            .file(ConfigFile::new(Path::new("test.txt"), StringFormat::new()))
+            .read()
+            .unwrap();
+        let ( _w, r ) = Watcher::channel(c, options(false)).unwrap();
+
+        fs::write(tp.child_path("test.txt"), "Goodbye, world!").unwrap();
+
+        match &r.recv_timeout(TIMEOUT).unwrap().unwrap().configs["test.txt"] {
+            Config::File(config_file) => assert_eq!(config_file.content.as_deref(), Some("Goodbye, world!")),
+            Config::Directory(_) => panic!("Expected a file")
+        }
+    }
+
+}
@@ -0,0 +1,87 @@
+use std::path::{ Path, PathBuf };
+use std::ffi::OsString;
+use std::env;
+
+/// XDG base directory, as in the XDG Base Directory Specification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaseDir {
+    /// Configs, `$XDG_CONFIG_HOME` or `~/.config` for the user and `$XDG_CONFIG_DIRS` or `/etc/xdg` system-wide
+    Config,
+    /// Data files, `$XDG_DATA_HOME` or `~/.local/share` for the user and `$XDG_DATA_DIRS` or `/usr/local/share:/usr/share` system-wide
+    Data,
+    /// State that persists between runs but isn't worth keeping elsewhere, `$XDG_STATE_HOME` or `~/.local/state`. Only for the user
+    State
+}
+
+impl BaseDir {
+
+    /// Directory of the user's files. `None` if neither the variable nor `$HOME` is set
+    pub fn home(self) -> Option<PathBuf> {
+        let ( var, default ): (&str, &str) = match self {
+            BaseDir::Config => ( "XDG_CONFIG_HOME", ".config" ),
+            BaseDir::Data => ( "XDG_DATA_HOME", ".local/share" ),
+            BaseDir::State => ( "XDG_STATE_HOME", ".local/state" )
+        };
+
+        // Relative paths are invalid and should be ignored, as the specification says
+        match env::var_os(var).map(PathBuf::from) {
+            Some(path) if path.is_absolute() => Some(path),
+            _ => env::var_os("HOME")
+                .filter(|home| !home.is_empty())
+                .map(|home| Path::new(&home).join(default))
+        }
+    }
+
+    /// System-wide directories, in order of priority
+    pub fn dirs(self) -> Vec<PathBuf> {
+        let ( var, default ): (&str, &str) = match self {
+            BaseDir::Config => ( "XDG_CONFIG_DIRS", "/etc/xdg" ),
+            BaseDir::Data => ( "XDG_DATA_DIRS", "/usr/local/share:/usr/share" ),
+            BaseDir::State => return Vec::new()
+        };
+        let dirs: Vec<PathBuf> = env::var_os(var)
+            .map(|dirs| env::split_paths(&dirs).filter(|path| path.is_absolute()).collect())
+            .unwrap_or_default();
+
+        if dirs.is_empty() {
+            env::split_paths(&OsString::from(default)).collect()
+        } else {
+            dirs
+        }
+    }
+
+    /// `path` in the user's directory, where it's written,
+    /// along with `path` in the system-wide directories, where it's read from when the user doesn't have it
+    pub(crate) fn paths(self, path: &Path) -> Option<( PathBuf, Vec<PathBuf> )> {
+        let home_path: PathBuf = self.home()?.join(path);
+        let fallbacks: Vec<PathBuf> = self.dirs().into_iter().map(|dir| dir.join(path)).collect();
+
+        Some(( home_path, fallbacks ))
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // The variables are shared by the whole process, so they're only changed in this one test
+    #[test]
+    fn resolve_paths() {
+        env::set_var("HOME", "/home/user");
+        env::remove_var("XDG_CONFIG_HOME");
+        env::remove_var("XDG_CONFIG_DIRS");
+        env::set_var("XDG_DATA_HOME", "relative");
+        env::set_var("XDG_DATA_DIRS", "/opt/share:relative:/usr/share");
+        env::set_var("XDG_STATE_HOME", "/var/state");
+
+        assert_eq!(BaseDir::Config.paths(Path::new("app/app.toml")), Some(( PathBuf::from("/home/user/.config/app/app.toml"), vec![
+            PathBuf::from("/etc/xdg/app/app.toml")
+        ] )));
+        assert_eq!(BaseDir::Data.home(), Some(PathBuf::from("/home/user/.local/share")));
+        assert_eq!(BaseDir::Data.dirs(), vec![ PathBuf::from("/opt/share"), PathBuf::from("/usr/share") ]);
+        assert_eq!(BaseDir::State.paths(Path::new("app")), Some(( PathBuf::from("/var/state/app"), vec![] )));
+    }
+
+}